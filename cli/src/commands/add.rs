@@ -0,0 +1,99 @@
+use crate::{
+    commands::GlobalOpts,
+    utils,
+    utils::{git, target::Target, user},
+};
+use anyhow::{ensure, Result};
+use nodo_core::{markdown::ParseOptions, BlockKind, Inline, Nodo};
+use std::{
+    fs,
+    io::{stdin, Read},
+};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct Add {
+    /// The nodo to add to, created if it doesn't exist
+    #[structopt(name = "TARGET")]
+    target: Target,
+
+    /// The text to add, read from stdin if not given
+    #[structopt(name = "TEXT")]
+    text: Vec<String>,
+
+    /// Add the text as an open task rather than a paragraph
+    #[structopt(short, long)]
+    task: bool,
+
+    /// Add under this heading, which is created if the nodo doesn't have it. Repeat to give a
+    /// path to a subheading, outermost first
+    #[structopt(short = "H", long, number_of_values = 1)]
+    heading: Vec<String>,
+}
+
+impl Add {
+    pub fn run(&self, g: &GlobalOpts) -> Result<()> {
+        let text = if self.text.is_empty() {
+            let mut buf = String::new();
+            stdin().read_to_string(&mut buf)?;
+            buf
+        } else {
+            self.text.join(" ")
+        };
+        let text = text.trim();
+        ensure!(!text.is_empty(), "Nothing to add");
+
+        let path = self.target.build_path(&g.root);
+        ensure!(
+            !path.exists() || path.is_file(),
+            "Target must be a nodo, not a directory"
+        );
+        let format = utils::format_of(&path)?;
+        let mut nodo = if path.exists() {
+            utils::read_nodo(&path)?
+        } else {
+            if let Some(p) = path.parent() {
+                fs::create_dir_all(p)?;
+            }
+            Nodo::default()
+        };
+
+        // the text is written in the format of the nodo it's added to
+        let added = format.parse(text, ParseOptions { lenient: true })?;
+        let heading = self.heading.iter().map(String::as_str).collect::<Vec<_>>();
+        if self.task {
+            nodo.append_task(&heading, task_text(text, &added));
+        } else {
+            for block in added.blocks() {
+                nodo.append(&heading, block.clone());
+            }
+        }
+        utils::write_nodo(&path, &nodo)?;
+
+        let name = path.strip_prefix(&g.root)?.display().to_string();
+        let msg = if self.task {
+            format!("Add task '{text}' to {name}")
+        } else {
+            format!("Add to {name}")
+        };
+        git::Repo::open(&g.root)?.commit_path(&path, &msg)?;
+        println!(
+            "Added {} to {}",
+            if self.task { "task" } else { "text" },
+            user::file_name_string(name)
+        );
+
+        Ok(())
+    }
+}
+
+/// The formatted text of a task, keeping markdown such as emphasis when the text is a single
+/// paragraph.
+fn task_text(text: &str, parsed: &Nodo) -> Vec<Inline> {
+    if let [block] = parsed.blocks() {
+        if let BlockKind::Paragraph(inlines) = block.kind() {
+            return inlines.clone();
+        }
+    }
+    vec![Inline::Plain(text.to_string())]
+}
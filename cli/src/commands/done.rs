@@ -0,0 +1,132 @@
+use crate::{
+    commands::GlobalOpts,
+    utils,
+    utils::{git, target::Target, user},
+};
+use anyhow::{bail, ensure, Result};
+use nodo_core::{query::tasks::Task, TaskState};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct Done {
+    #[structopt(flatten)]
+    task: TaskRef,
+}
+
+impl Done {
+    pub fn run(&self, g: &GlobalOpts) -> Result<()> {
+        self.task.set_state(g, |_| TaskState::Done)
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct Toggle {
+    #[structopt(flatten)]
+    task: TaskRef,
+}
+
+impl Toggle {
+    pub fn run(&self, g: &GlobalOpts) -> Result<()> {
+        self.task.set_state(g, |state| match state {
+            TaskState::Done => TaskState::Open,
+            TaskState::Open
+            | TaskState::InProgress
+            | TaskState::Cancelled
+            | TaskState::Deferred => TaskState::Done,
+        })
+    }
+}
+
+/// A task in a nodo, given by its index as listed by `nodo tasks` or by some of its text.
+#[derive(StructOpt, Debug)]
+struct TaskRef {
+    /// The nodo containing the task
+    #[structopt(name = "TARGET")]
+    target: Target,
+
+    /// The index of the task in the nodo or some of its text
+    #[structopt(name = "TASK")]
+    task: String,
+}
+
+impl TaskRef {
+    fn set_state(&self, g: &GlobalOpts, state: impl FnOnce(TaskState) -> TaskState) -> Result<()> {
+        let path = self.target.build_path(&g.root);
+        ensure!(path.is_file(), "Target must be an existing nodo");
+
+        let mut nodo = utils::read_nodo(&path)?;
+        let tasks = nodo.tasks();
+        let index = self.find(&tasks)?;
+        let task = &tasks[index];
+
+        let state = state(task.state);
+        if state == task.state {
+            println!("Task '{}' is already {}", task.text, describe(state));
+            return Ok(());
+        }
+        nodo.set_task_state(index, state);
+        utils::write_nodo(&path, &nodo)?;
+
+        let name = path.strip_prefix(&g.root)?.display().to_string();
+        let verb = match state {
+            TaskState::Open => "Reopen",
+            TaskState::Done => "Complete",
+            TaskState::InProgress => "Start",
+            TaskState::Cancelled => "Cancel",
+            TaskState::Deferred => "Defer",
+        };
+        git::Repo::open(&g.root)?
+            .commit_path(&path, &format!("{} task '{}' in {}", verb, task.text, name))?;
+        println!(
+            "Marked '{}' as {} in {}",
+            task.text,
+            describe(state),
+            user::file_name_string(name)
+        );
+
+        Ok(())
+    }
+
+    /// The index of the referenced task, which must be unambiguous when matching on text.
+    fn find(&self, tasks: &[Task]) -> Result<usize> {
+        if let Ok(index) = self.task.parse::<usize>() {
+            ensure!(
+                index < tasks.len(),
+                "No task {}, the nodo has {} tasks",
+                index,
+                tasks.len()
+            );
+            return Ok(index);
+        }
+
+        let needle = self.task.to_lowercase();
+        let matches = tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.text.to_lowercase().contains(&needle))
+            .collect::<Vec<_>>();
+        match matches.as_slice() {
+            [] => bail!("No task matching '{}'", self.task),
+            [(index, _)] => Ok(*index),
+            _ => bail!(
+                "Several tasks match '{}', use an index instead:\n{}",
+                self.task,
+                matches
+                    .iter()
+                    .map(|(i, t)| format!("  {}. {}", i, t.text))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+        }
+    }
+}
+
+const fn describe(state: TaskState) -> &'static str {
+    match state {
+        TaskState::Open => "open",
+        TaskState::Done => "done",
+        TaskState::InProgress => "in progress",
+        TaskState::Cancelled => "cancelled",
+        TaskState::Deferred => "deferred",
+    }
+}
@@ -1,11 +1,11 @@
 use crate::{
     commands::GlobalOpts,
+    utils,
     utils::{git, target::Target, user},
 };
 use anyhow::{ensure, Result};
-use log::{debug, info};
-use nodo_core::{Markdown, Parse, Render};
-use std::{env, fs, fs::File, io::Read, path::Path, process};
+use log::{debug, info, warn};
+use std::{env, fs, fs::File, path::Path, process};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -66,11 +66,17 @@ fn edit_nodo(path: &Path, root: &Path) -> Result<()> {
         "Error occurred when editing. Try running with more verbosity (-v) for more information."
     );
 
-    // format the just edited nodo
-    let mut buf = String::new();
-    File::read_to_string(&mut File::open(&path)?, &mut buf)?;
-    let nodo = Markdown::parse(&buf)?;
-    Markdown::render(&nodo, &mut File::create(&path)?)?;
+    // format the just edited nodo, leaving files in unsupported formats as they were written
+    if utils::FORMATS.by_path(path).is_some() {
+        let nodo = utils::read_nodo(path)?;
+        utils::warn_unparsed(path, &nodo);
+        utils::write_nodo(path, &nodo)?;
+    } else {
+        warn!(
+            "Not formatting {} as its format is unsupported",
+            path.display()
+        );
+    }
 
     commit_changes(path, root)?;
 
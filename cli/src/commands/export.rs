@@ -0,0 +1,268 @@
+use crate::{
+    commands::{show, GlobalOpts},
+    utils,
+    utils::user,
+};
+use anyhow::{ensure, Context, Result};
+use log::debug;
+use nodo_core::{
+    html::{escape, RenderOptions},
+    visit::{walk_inline_mut, VisitMut},
+    Html, Inline,
+};
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct Export {
+    /// Export the nodos as a static html site in this directory
+    #[structopt(long, name = "DIR")]
+    html: PathBuf,
+}
+
+const STYLE: &str = "body { max-width: 50em; margin: auto; padding: 1em; font-family: sans-serif; }
+li.task { list-style: none; }
+li.task.cancelled { text-decoration: line-through; }";
+
+impl Export {
+    pub fn run(&self, g: &GlobalOpts) -> Result<()> {
+        let root = g.root.canonicalize()?;
+        let out = absolute(&self.html)?;
+        // the export would otherwise be exported itself, or write over the nodos
+        ensure!(
+            !out.starts_with(&root),
+            "Export directory {} must be outside of the nodo root",
+            self.html.display()
+        );
+
+        fs::create_dir_all(&out).context("Failed to create export directory")?;
+        let mut site = Site {
+            root,
+            out,
+            nodos: 0,
+        };
+        let root = site.root.clone();
+        site.export_dir(&root)?;
+
+        println!("Exported {} nodos to {}", site.nodos, self.html.display());
+        Ok(())
+    }
+}
+
+/// The export of the nodos in the root directory as html pages.
+struct Site {
+    root: PathBuf,
+    out: PathBuf,
+    /// The number of nodos exported so far.
+    nodos: usize,
+}
+
+impl Site {
+    /// Export a directory along with an index page listing its entries, as `nodo show` does.
+    fn export_dir(&mut self, dir: &Path) -> Result<()> {
+        debug!("Exporting directory {}", dir.display());
+        let out_dir = self.out.join(dir.strip_prefix(&self.root)?);
+        fs::create_dir_all(&out_dir)?;
+
+        let mut entries = String::new();
+        for entry in show::read_dir_sorted(dir)? {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == ".git" {
+                continue;
+            }
+            if dir == self.root && utils::is_hidden_dir(&name) {
+                continue;
+            }
+
+            if path.is_dir() {
+                self.export_dir(&path)?;
+                let (files, directories) = show::count_entries(&path)?;
+                write!(
+                    entries,
+                    "<li><a href=\"{}/index.html\">{}/</a>",
+                    escape(&href(&name)),
+                    escape(&name)
+                )?;
+                if files > 0 || directories > 0 {
+                    let description = show::describe_entries(files, directories);
+                    write!(entries, " [{}]", escape(&description))?;
+                }
+            } else if utils::FORMATS.by_path(&path).is_some() {
+                let Some(summary) = self.export_nodo(&path, &out_dir)? else {
+                    continue;
+                };
+                write!(
+                    entries,
+                    "<li><a href=\"{}\">{}</a>{}",
+                    escape(&href(&page_name(Path::new(&name)).to_string_lossy())),
+                    escape(&name),
+                    summary
+                )?;
+            } else {
+                // other files, such as images, are linked to from nodos as they are
+                fs::copy(&path, out_dir.join(&name))?;
+                write!(
+                    entries,
+                    "<li><a href=\"{}\">{}</a>",
+                    escape(&href(&name)),
+                    escape(&name)
+                )?;
+            }
+            entries.push_str("</li>\n");
+        }
+
+        let title = if dir == self.root {
+            "nodo".to_string()
+        } else {
+            dir.strip_prefix(&self.root)?.display().to_string()
+        };
+        let nav = if dir == self.root {
+            ""
+        } else {
+            "<nav><a href=\"../index.html\">..</a></nav>\n"
+        };
+        fs::write(
+            out_dir.join("index.html"),
+            page(&title, &format!("{nav}<ul>\n{entries}</ul>\n")),
+        )?;
+        Ok(())
+    }
+
+    /// Export a nodo as a page, returning the summary of its tasks for the index, or `None` if
+    /// the nodo couldn't be read and was skipped.
+    fn export_nodo(&mut self, path: &Path, out_dir: &Path) -> Result<Option<String>> {
+        debug!("Exporting nodo {}", path.display());
+        let mut nodo = match utils::read_nodo(path) {
+            Ok(nodo) => nodo,
+            Err(e) => {
+                user::warn(format!("{e:#}, so it isn't exported"));
+                return Ok(None);
+            }
+        };
+        LinkRewriter.visit_nodo_mut(&mut nodo);
+
+        let mut content = Vec::new();
+        // raw html in a nodo could run script in the exported site, so it is written as text
+        Html::render_with(&nodo, &mut content, RenderOptions { escape_html: true })?;
+        let title = nodo.metadata().and_then(|m| m.title()).map_or_else(
+            || path.file_stem().unwrap_or_default().to_string_lossy(),
+            Into::into,
+        );
+        let body = format!(
+            "<nav><a href=\"index.html\">..</a></nav>\n{}",
+            String::from_utf8(content)?
+        );
+        let name = page_name(Path::new(path.file_name().unwrap_or_default()));
+        fs::write(out_dir.join(name), page(&title, &body))?;
+        self.nodos += 1;
+
+        let task_count = nodo.count_tasks();
+        let mut summary = String::new();
+        if task_count.total > 0 {
+            write!(
+                summary,
+                " [{}/{} ({}%)]",
+                task_count.completed,
+                task_count.total,
+                show::task_percentage(&task_count)
+            )?;
+            if task_count.in_progress > 0 {
+                write!(summary, " [{} in progress]", task_count.in_progress)?;
+            }
+        }
+        Ok(Some(summary))
+    }
+}
+
+/// The absolute form of a path, which unlike [`Path::canonicalize`] doesn't have to exist yet.
+fn absolute(path: &Path) -> Result<PathBuf> {
+    if path.exists() {
+        return Ok(path.canonicalize()?);
+    }
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => absolute(p)?,
+        _ => env::current_dir()?,
+    };
+    Ok(parent.join(path.file_name().unwrap_or_default()))
+}
+
+/// A complete html page with the given title and body.
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{}</title>
+<style>
+{}
+</style>
+</head>
+<body>
+{}</body>
+</html>
+",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+/// The name of the page for a nodo, keeping clear of the index page of its directory.
+fn page_name(path: &Path) -> PathBuf {
+    if path.file_stem().is_some_and(|s| s == "index") {
+        path.with_extension("nodo.html")
+    } else {
+        path.with_extension("html")
+    }
+}
+
+/// A file name as part of a link, with the characters that would change the meaning of the link
+/// encoded.
+fn href(name: &str) -> String {
+    name.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('#', "%23")
+        .replace('?', "%3F")
+}
+
+/// Points relative links between nodos at their exported pages.
+struct LinkRewriter;
+
+impl VisitMut for LinkRewriter {
+    fn visit_inline_mut(&mut self, i: &mut Inline) {
+        if let Inline::Link(link) = i {
+            if let Some(destination) = page_link(&link.destination) {
+                link.destination = destination;
+            }
+        }
+        walk_inline_mut(self, i);
+    }
+}
+
+/// The link to the page of the nodo at `destination`, if it is a relative link to a nodo.
+fn page_link(destination: &str) -> Option<String> {
+    // org links to files with a `file:` scheme, which the page doesn't need
+    let destination = destination.strip_prefix("file:").unwrap_or(destination);
+    let is_relative = !(destination.contains("://")
+        || destination.starts_with('/')
+        || destination.starts_with('#')
+        || destination.starts_with("mailto:"));
+    if !is_relative {
+        return None;
+    }
+
+    let (path, fragment) = destination
+        .find('#')
+        .map_or((destination, ""), |i| destination.split_at(i));
+    let path = Path::new(path);
+    utils::FORMATS.by_path(path)?;
+    Some(format!("{}{}", page_name(path).display(), fragment))
+}
@@ -1,21 +1,20 @@
-use lazy_static::lazy_static;
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::LazyLock};
 use structopt::StructOpt;
 
+mod add;
 mod completions;
+mod done;
 mod edit;
+mod export;
 mod r#move;
 mod remove;
 pub mod show;
 mod sync;
+mod tasks;
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "nodo")]
 pub struct Opts {
-    /// Change the verbosity, repeat for higher levels
-    #[structopt(short, long, parse(from_occurrences), global = true)]
-    pub verbose: u32,
-
     #[structopt(flatten)]
     pub globals: GlobalOpts,
 
@@ -23,14 +22,17 @@ pub struct Opts {
     pub subcommand: Option<SubCommand>,
 }
 
-lazy_static! {
-    static ref DATA_DIR: String = dirs::data_dir().unwrap().join("nodo").display().to_string();
-}
+static DATA_DIR: LazyLock<String> =
+    LazyLock::new(|| dirs::data_dir().unwrap().join("nodo").display().to_string());
 
 #[derive(StructOpt, Debug)]
 pub struct GlobalOpts {
+    /// Change the verbosity, repeat for higher levels
+    #[structopt(short, long, parse(from_occurrences), global = true)]
+    pub verbose: u32,
+
     /// The root directory for storing nodos
-    #[structopt(long, default_value = &DATA_DIR, env("NODO_ROOT"), global = true)]
+    #[structopt(long, default_value = &**DATA_DIR, env("NODO_ROOT"), global = true)]
     pub root: PathBuf,
 }
 
@@ -39,6 +41,9 @@ pub enum SubCommand {
     /// Edit an existing nodo, or create a new one
     Edit(edit::Edit),
 
+    /// Add text or a task to a nodo without opening an editor
+    Add(add::Add),
+
     /// Show the existing nodos
     Show(show::Show),
 
@@ -48,6 +53,18 @@ pub enum SubCommand {
     /// Move a nodo or directory
     Move(r#move::Move),
 
+    /// Mark a task in a nodo as done
+    Done(done::Done),
+
+    /// Mark a task in a nodo as done, or open again if it is already done
+    Toggle(done::Toggle),
+
+    /// List the tasks across all nodos
+    Tasks(tasks::Tasks),
+
+    /// Export the nodos, such as to a static html site
+    Export(export::Export),
+
     /// Sync the nodo repository
     Sync(sync::Sync),
 
@@ -7,8 +7,8 @@ use anyhow::{ensure, Result};
 use bat::PrettyPrinter;
 use colored::Colorize;
 use log::debug;
-use nodo_core::{Markdown, Parse};
-use std::{cmp::Ordering, fs, fs::File, io::Read, path::Path};
+use nodo_core::query::tasks::TaskCount;
+use std::{cmp::Ordering, fs, path::Path};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -66,13 +66,13 @@ impl Show {
         ensure!(target.exists(), "Target does not exist");
 
         if target.is_dir() {
-            self.print_tree(&target, self.target.is_none(), self.depth)
+            self.print_tree(&target, self.target.is_none(), self.depth, g.verbose > 0)
         } else {
             self.print_nodo(&target)
         }
     }
 
-    fn print_tree(&self, target: &Path, is_root: bool, depth: i32) -> Result<()> {
+    fn print_tree(&self, target: &Path, is_root: bool, depth: i32, verbose: bool) -> Result<()> {
         debug!("Printing tree from root {}", target.display());
         for entry in read_dir_sorted(target)? {
             if entry.file_name().to_string_lossy() == ".git" {
@@ -84,9 +84,9 @@ impl Show {
             let path = entry.path();
             if path.is_dir() {
                 print_dir_name(&path, depth)?;
-                print_dir(&path, "", depth - 1)?
+                print_dir(&path, "", depth - 1, verbose)?;
             } else {
-                print_nodo_summary(&path)?
+                print_nodo_summary(&path, verbose);
             }
         }
 
@@ -105,7 +105,7 @@ impl Show {
     }
 }
 
-fn read_dir_sorted(path: &Path) -> Result<Vec<fs::DirEntry>> {
+pub fn read_dir_sorted(path: &Path) -> Result<Vec<fs::DirEntry>> {
     let mut entries = Vec::new();
     for entry in fs::read_dir(path)? {
         entries.push(entry?)
@@ -119,43 +119,56 @@ fn read_dir_sorted(path: &Path) -> Result<Vec<fs::DirEntry>> {
     Ok(entries)
 }
 
+/// The number of files and directories directly within a directory.
+pub fn count_entries(path: &Path) -> Result<(usize, usize)> {
+    let mut counts = (0, 0);
+    for entry in fs::read_dir(path)? {
+        if entry?.path().is_dir() {
+            counts.1 += 1;
+        } else {
+            counts.0 += 1;
+        }
+    }
+    Ok(counts)
+}
+
+/// A description of the entries in a directory such as `2 files, 1 directory`.
+pub fn describe_entries(files: usize, directories: usize) -> String {
+    let mut parts = Vec::new();
+    match files.cmp(&1) {
+        Ordering::Greater => parts.push(format!("{files} files")),
+        Ordering::Equal => parts.push(format!("{files} file")),
+        Ordering::Less => {}
+    }
+    match directories.cmp(&1) {
+        Ordering::Greater => parts.push(format!("{directories} directories")),
+        Ordering::Equal => parts.push(format!("{directories} directory")),
+        Ordering::Less => {}
+    }
+    parts.join(", ")
+}
+
+/// The percentage of tasks that are completed, rounded down.
+pub fn task_percentage(task_count: &TaskCount) -> f64 {
+    (100_f64 * (f64::from(task_count.completed) / f64::from(task_count.total))).trunc()
+}
+
 fn print_dir_name(path: &Path, depth: i32) -> Result<()> {
     print!(
         "{}",
         user::dir_name_string(&path.file_name().unwrap().to_string_lossy())
     );
     if depth == 1 {
-        let (files, directories) = fs::read_dir(&path)?.fold((0, 0), |(f, d), e| {
-            if e.unwrap().path().is_dir() {
-                (f, d + 1)
-            } else {
-                (f + 1, d)
-            }
-        });
+        let (files, directories) = count_entries(path)?;
         if files > 0 || directories > 0 {
-            print!(" [");
-            match files.cmp(&1) {
-                Ordering::Greater => print!("{} files", files),
-                Ordering::Equal => print!("{} file", files),
-                Ordering::Less => {}
-            }
-            if files > 0 && directories > 0 {
-                print!(", ")
-            }
-            match directories.cmp(&1) {
-                Ordering::Greater => print!("{} directories", directories),
-                Ordering::Equal => print!("{} directory", directories),
-                Ordering::Less => {}
-            }
-
-            print!("]");
+            print!(" [{}]", describe_entries(files, directories));
         }
     }
     println!();
     Ok(())
 }
 
-fn print_dir(path: &Path, prefix: &str, depth: i32) -> Result<()> {
+fn print_dir(path: &Path, prefix: &str, depth: i32, verbose: bool) -> Result<()> {
     if depth == 0 {
         return Ok(());
     }
@@ -168,17 +181,17 @@ fn print_dir(path: &Path, prefix: &str, depth: i32) -> Result<()> {
             print!("{}\u{2514}\u{2500} ", prefix);
             if path.is_dir() {
                 print_dir_name(&path, depth)?;
-                print_dir(&path, &format!("{}   ", prefix), depth - 1)?
+                print_dir(&path, &format!("{prefix}   "), depth - 1, verbose)?;
             } else {
-                print_nodo_summary(&path)?
+                print_nodo_summary(&path, verbose);
             }
         } else {
             print!("{}\u{251c}\u{2500} ", prefix);
             if path.is_dir() {
                 print_dir_name(&path, depth)?;
-                print_dir(&path, &format!("{}\u{2502}  ", prefix), depth - 1)?
+                print_dir(&path, &format!("{prefix}\u{2502}  "), depth - 1, verbose)?;
             } else {
-                print_nodo_summary(&path)?;
+                print_nodo_summary(&path, verbose);
             }
         }
     }
@@ -186,22 +199,31 @@ fn print_dir(path: &Path, prefix: &str, depth: i32) -> Result<()> {
     Ok(())
 }
 
-fn print_nodo_summary(path: &Path) -> Result<()> {
-    let mut buf = String::new();
-    File::open(path)?.read_to_string(&mut buf)?;
-    let nodo = Markdown::parse(&buf)?;
-    let task_count = nodo.count_tasks();
+/// Print the name of a nodo along with a count of its tasks, and when `verbose` any warnings
+/// about where it couldn't be parsed.
+fn print_nodo_summary(path: &Path, verbose: bool) {
     print!(
         "{}{}",
         user::file_name_string(&path.file_stem().unwrap().to_string_lossy()),
         path.extension()
             .map_or_else(String::new, |e| format!(".{}", e.to_string_lossy()))
     );
+    // other files can live alongside nodos, they just don't have tasks to count
+    if utils::FORMATS.by_path(path).is_none() {
+        println!();
+        return;
+    }
+    let nodo = match utils::read_nodo(path) {
+        Ok(nodo) => nodo,
+        Err(e) => {
+            println!();
+            user::warn(format!("{e:#}"));
+            return;
+        }
+    };
+    let task_count = nodo.count_tasks();
     if task_count.total > 0 {
-        let task_percentage = format!(
-            "{}%",
-            (100_f64 * (f64::from(task_count.completed) / f64::from(task_count.total))).trunc()
-        );
+        let task_percentage = format!("{}%", task_percentage(&task_count));
         print!(
             " [{}/{} ({})]",
             task_count.completed,
@@ -213,8 +235,17 @@ fn print_nodo_summary(path: &Path) -> Result<()> {
             } else {
                 task_percentage.red().bold()
             }
-        )
+        );
+        if task_count.in_progress > 0 {
+            print!(
+                " [{} in progress]",
+                task_count.in_progress.to_string().blue().bold()
+            );
+        }
     }
     println!();
-    Ok(())
+    // warn once the summary is written so as not to break up its line
+    if verbose {
+        utils::warn_unparsed(path, &nodo);
+    }
 }
@@ -0,0 +1,162 @@
+use crate::{commands::GlobalOpts, utils, utils::user};
+use anyhow::{bail, Result};
+use colored::Colorize;
+use nodo_core::{
+    query::tasks::{Date, Task},
+    TaskState,
+};
+use std::path::Path;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct Tasks {
+    /// Only show tasks in nodos whose path from the root starts with this, such as `work/`
+    #[structopt(short, long)]
+    path: Option<String>,
+
+    /// Only show tasks with this status: open, done, in-progress, cancelled or deferred [default:
+    /// open, in-progress and deferred]
+    #[structopt(short, long, parse(try_from_str = parse_state))]
+    status: Vec<TaskState>,
+
+    /// Only show tasks with this tag, repeat to require several tags
+    #[structopt(short, long)]
+    tag: Vec<String>,
+
+    /// Only show tasks due on or before this date, given as YYYY-MM-DD
+    #[structopt(short, long)]
+    due: Option<Date>,
+
+    /// List every task on its own line with its location rather than grouping them by nodo
+    #[structopt(short, long)]
+    flat: bool,
+}
+
+impl Tasks {
+    pub fn run(&self, g: &GlobalOpts) -> Result<()> {
+        for (name, tasks) in self.nodo_tasks(&g.root)? {
+            let matching = tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| self.matches(t))
+                .collect::<Vec<_>>();
+            if matching.is_empty() {
+                continue;
+            }
+
+            if self.flat {
+                for (index, task) in matching {
+                    print!("{}:{} ", user::file_name_string(&name), task.line);
+                    print_task(index, task);
+                }
+            } else {
+                println!("{}", user::file_name_string(&name));
+                for (index, task) in matching {
+                    print!("{}", "  ".repeat(depth(&tasks, task) + 1));
+                    print_task(index, task);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The tasks of each nodo under `root` within the path filter, by the path of the nodo from
+    /// the root. Nodos that can't be read are skipped with a warning.
+    fn nodo_tasks(&self, root: &Path) -> Result<Vec<(String, Vec<Task>)>> {
+        let mut nodos = Vec::new();
+        for path in utils::walk_nodos(root)? {
+            let name = path.strip_prefix(root)?.display().to_string();
+            if !self.path.as_ref().is_none_or(|p| name.starts_with(p)) {
+                continue;
+            }
+
+            match utils::read_nodo(&path) {
+                Ok(nodo) => nodos.push((name, nodo.tasks())),
+                Err(e) => user::warn(format!("{e:#}, so its tasks aren't listed")),
+            }
+        }
+        Ok(nodos)
+    }
+
+    fn matches(&self, task: &Task) -> bool {
+        let status = if self.status.is_empty() {
+            !matches!(task.state, TaskState::Done | TaskState::Cancelled)
+        } else {
+            self.status.contains(&task.state)
+        };
+        status
+            && self.tag.iter().all(|t| task.tags.contains(t))
+            && self
+                .due
+                .is_none_or(|due| task.due.is_some_and(|d| d <= due))
+    }
+}
+
+fn parse_state(s: &str) -> Result<TaskState> {
+    Ok(match s {
+        "open" => TaskState::Open,
+        "done" => TaskState::Done,
+        "in-progress" => TaskState::InProgress,
+        "cancelled" => TaskState::Cancelled,
+        "deferred" => TaskState::Deferred,
+        _ => bail!(
+            "unknown status '{}', expected open, done, in-progress, cancelled or deferred",
+            s
+        ),
+    })
+}
+
+/// The number of tasks that `task` is nested beneath.
+fn depth(tasks: &[Task], task: &Task) -> usize {
+    std::iter::successors(task.parent, |&p| tasks[p].parent).count()
+}
+
+/// Print a task along with its index, which `nodo done` and `nodo toggle` take to address it.
+fn print_task(index: usize, task: &Task) {
+    let marker = format!("[{}]", task.state.marker());
+    print!(
+        "{} {} {}",
+        format!("{index}.").dimmed(),
+        match task.state {
+            TaskState::Done => marker.green(),
+            TaskState::InProgress => marker.yellow(),
+            TaskState::Open | TaskState::Cancelled | TaskState::Deferred => marker.normal(),
+        },
+        task.text
+    );
+    if !task.headings.is_empty() {
+        print!(" {}", format!("({})", task.headings.join(" > ")).dimmed());
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn skip_unreadable_nodos() {
+        let root = std::env::temp_dir().join(format!("nodo-tasks-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("good.md"), "- [ ] readable\n").unwrap();
+        // not utf-8, so it can't be read as a nodo
+        fs::write(root.join("bad.md"), b"- [ ] \xff\n").unwrap();
+
+        let tasks = Tasks {
+            path: None,
+            status: Vec::new(),
+            tag: Vec::new(),
+            due: None,
+            flat: false,
+        };
+        let nodos = tasks.nodo_tasks(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        let nodos = nodos.unwrap();
+        assert_eq!(nodos.len(), 1);
+        assert_eq!(nodos[0].0, "good.md");
+        assert_eq!(nodos[0].1[0].text, "readable");
+    }
+}
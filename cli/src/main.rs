@@ -14,7 +14,7 @@ use structopt::StructOpt;
 fn main() -> Result<()> {
     let opts = Opts::from_args();
 
-    let log_level = match opts.verbose {
+    let log_level = match opts.globals.verbose {
         0 => None,
         1 => Some(Level::Error),
         2 => Some(Level::Warn),
@@ -38,9 +38,14 @@ fn main() -> Result<()> {
         None => Show::default().run(&opts.globals),
         Some(cmd) => match cmd {
             SubCommand::Edit(e) => e.run(&opts.globals),
+            SubCommand::Add(a) => a.run(&opts.globals),
             SubCommand::Show(s) => s.run(&opts.globals),
             SubCommand::Remove(r) => r.run(&opts.globals),
             SubCommand::Move(m) => m.run(&opts.globals),
+            SubCommand::Done(d) => d.run(&opts.globals),
+            SubCommand::Toggle(t) => t.run(&opts.globals),
+            SubCommand::Tasks(t) => t.run(&opts.globals),
+            SubCommand::Export(e) => e.run(&opts.globals),
             SubCommand::Sync(s) => s.run(&opts.globals),
             SubCommand::Completions(c) => c.run(),
         },
@@ -1,6 +1,6 @@
 use crate::utils::user;
 use anyhow::{bail, ensure, Result};
-use git2::{ErrorCode, Repository, Status};
+use git2::{ErrorCode, Index, Repository, Status};
 use std::path::Path;
 
 pub struct Repo {
@@ -128,4 +128,32 @@ impl Repo {
 
         Ok(())
     }
+
+    /// Commit only the changes to `path`, with the given message rather than one listing the
+    /// changed paths. Anything else staged is left as it is.
+    pub fn commit_path(&self, path: &Path, msg: &str) -> Result<()> {
+        let root = self.repo.workdir().unwrap();
+        let rel_path = path.strip_prefix(root)?;
+
+        let mut index = self.repo.index()?;
+        index.add_path(rel_path)?;
+        index.write()?;
+        let entry = index.get_path(rel_path, 0).unwrap();
+
+        let parent = self.repo.head()?.peel_to_commit()?;
+        let mut changes = Index::new()?;
+        changes.read_tree(&parent.tree()?)?;
+        changes.add(&entry)?;
+        let tree_oid = changes.write_tree_to(&self.repo)?;
+        if tree_oid == parent.tree_id() {
+            return Ok(());
+        }
+        let tree = self.repo.find_tree(tree_oid)?;
+
+        let signature = self.repo.signature()?;
+        self.repo
+            .commit(Some("HEAD"), &signature, &signature, msg, &tree, &[&parent])?;
+
+        Ok(())
+    }
 }
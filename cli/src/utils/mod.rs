@@ -1,3 +1,12 @@
+use anyhow::{Context, Result};
+use nodo_core::{markdown::ParseOptions, registry::FileFormat, Nodo, Registry};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
 pub mod git;
 pub mod target;
 pub mod user;
@@ -5,3 +14,83 @@ pub mod user;
 pub const fn is_hidden_dir(_name: &str) -> bool {
     false
 }
+
+pub static FORMATS: LazyLock<Registry> = LazyLock::new(Registry::default);
+
+/// The format of the nodo at `path`, if it is one that nodos can be stored in.
+pub fn format_of(path: &Path) -> Result<&'static FileFormat> {
+    FORMATS
+        .by_path(path)
+        .with_context(|| format!("Unsupported nodo format for {}", path.display()))
+}
+
+/// Read and parse the nodo at `path` in the format for its extension.
+///
+/// Anything that can't be parsed is kept as raw source rather than failing the whole nodo.
+pub fn read_nodo(path: &Path) -> Result<Nodo> {
+    let buf =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    format_of(path)?
+        .parse(&buf, ParseOptions { lenient: true })
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Warn about where the nodo read from `path` couldn't be parsed, which [`read_nodo`] keeps as
+/// it was written.
+pub fn warn_unparsed(path: &Path, nodo: &Nodo) {
+    if !FORMATS.by_path(path).is_some_and(FileFormat::is_lenient) {
+        return;
+    }
+    for span in nodo.raw_spans() {
+        user::warn(format!(
+            "{}:{}:{} is kept as written as it can't be parsed",
+            path.display(),
+            span.start.line,
+            span.start.column
+        ));
+    }
+}
+
+/// Render a nodo to `path` in the format for its extension.
+///
+/// The nodo is rendered in full before anything is written, then written to a temporary file
+/// that replaces `path`, so a failure part of the way through leaves the old nodo in place.
+pub fn write_nodo(path: &Path, nodo: &Nodo) -> Result<()> {
+    let mut buf = Vec::new();
+    format_of(path)?
+        .render(nodo, &mut buf)
+        .with_context(|| format!("Failed to render {}", path.display()))?;
+
+    // keep the temporary file in the same directory so the rename doesn't cross filesystems
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
+    fs::write(&tmp, buf)
+        .and_then(|()| fs::rename(&tmp, path))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// All of the nodos beneath `dir`, sorted by path and skipping the git directory and any files
+/// in formats that aren't supported.
+pub fn walk_nodos(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut nodos = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == ".git" || is_hidden_dir(&name.to_string_lossy()) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            nodos.extend(walk_nodos(&path)?);
+        } else if FORMATS.by_path(&path).is_some() {
+            nodos.push(path);
+        }
+    }
+    nodos.sort();
+    Ok(nodos)
+}
@@ -1,4 +1,6 @@
+use crate::utils::FORMATS;
 use log::debug;
+use nodo_core::registry::FileFormat;
 use std::{
     path::{Path, PathBuf},
     str::FromStr,
@@ -39,7 +41,7 @@ impl Target {
             );
             full_path
         } else {
-            let path = with_md_extension(&full_path);
+            let path = with_extension(&full_path);
             debug!(
                 "Built full path with extension since argument didn't exist: {:?}",
                 path
@@ -49,9 +51,16 @@ impl Target {
     }
 }
 
-fn with_md_extension(path: &Path) -> PathBuf {
-    match path.extension() {
-        None => path.with_extension("md"),
-        Some(_) => path.to_owned(),
+/// Add the extension of an existing nodo in any supported format, or the markdown extension for
+/// a new one.
+fn with_extension(path: &Path) -> PathBuf {
+    if path.extension().is_some() {
+        return path.to_owned();
     }
+    FORMATS
+        .formats()
+        .flat_map(FileFormat::extensions)
+        .map(|e| path.with_extension(e))
+        .find(|p| p.exists())
+        .unwrap_or_else(|| path.with_extension("md"))
 }
@@ -1,6 +1,9 @@
 use anyhow::Result;
 use colored::Colorize;
-use std::io::{stdin, stdout, BufRead, Write};
+use std::{
+    fmt::Display,
+    io::{stdin, stdout, BufRead, Write},
+};
 
 pub fn confirm(prompt: &str) -> Result<bool> {
     print!("{} [{}/n]: ", prompt, "Y".bold());
@@ -29,6 +32,11 @@ pub fn input(prompt: &str, default: Option<&str>) -> Result<String> {
     Ok(input.trim().to_string())
 }
 
+/// Tell the user about something that went wrong without stopping the command.
+pub fn warn<D: Display>(message: D) {
+    eprintln!("{} {}", "warning:".yellow().bold(), message);
+}
+
 pub fn dir_name_string<S: AsRef<str>>(name: S) -> String {
     format!("{}", name.as_ref().blue().bold())
 }
@@ -0,0 +1,491 @@
+//! Rendering of nodos to html, for publishing them or showing them in a web frontend.
+//!
+//! Only the content of a nodo is rendered, as a fragment to embed in a page, so its metadata is
+//! left for the page to show. Headings get ids from their text so that they can be linked to and
+//! tasks become disabled checkboxes. Links that would run script, such as `javascript:` links,
+//! are written as their text.
+//!
+//! Raw html in a nodo is written as it is by default, so it can still run script. Nodos that
+//! aren't trusted should be rendered with [`RenderOptions::escape_html`], which writes it as
+//! text instead.
+
+use crate::{
+    query::plain_text, visit::Visit, Alignment, Block, BlockKind, Inline, Link, LinkType, ListItem,
+    ListType, Nodo, Render, StringRenderer, TableRow, TaskState,
+};
+use std::{collections::HashMap, io};
+use thiserror::Error;
+
+pub struct Html;
+
+/// Options controlling how html is rendered.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct RenderOptions {
+    /// Write raw html blocks and inlines as text rather than as html.
+    pub escape_html: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum RenderError {
+    #[error("failed to write content: {0}")]
+    WriteFailure(#[from] io::Error),
+}
+
+/// Escape text for use in html content or in a quoted attribute value.
+#[must_use]
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The id of a heading with the given text, lowercased with words joined by dashes as on
+/// GitHub.
+#[must_use]
+pub fn slug(text: &str) -> String {
+    text.trim()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' | '-' => Some('-'),
+            '_' => Some('_'),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Whether a url would run script or load arbitrary content when followed, such as
+/// `javascript:alert(1)`.
+fn is_script_url(url: &str) -> bool {
+    // browsers skip whitespace and control characters in urls, as in `java\tscript:`
+    let url = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>();
+    url.split_once(':').is_some_and(|(scheme, _)| {
+        ["javascript", "vbscript", "data"]
+            .iter()
+            .any(|s| scheme.eq_ignore_ascii_case(s))
+    })
+}
+
+const fn state_class(state: TaskState) -> &'static str {
+    match state {
+        TaskState::Open => "open",
+        TaskState::Done => "done",
+        TaskState::InProgress => "in-progress",
+        TaskState::Cancelled => "cancelled",
+        TaskState::Deferred => "deferred",
+    }
+}
+
+const fn alignment_style(alignment: Alignment) -> &'static str {
+    match alignment {
+        Alignment::None => "",
+        Alignment::Left => " style=\"text-align: left\"",
+        Alignment::Center => " style=\"text-align: center\"",
+        Alignment::Right => " style=\"text-align: right\"",
+    }
+}
+
+struct Renderer {
+    out: String,
+    /// The number of headings that have been given each id, to keep the ids unique.
+    ids: HashMap<String, usize>,
+    escape_html: bool,
+}
+
+impl StringRenderer for Renderer {
+    fn out(&mut self) -> &mut String {
+        &mut self.out
+    }
+}
+
+impl Renderer {
+    fn new(options: RenderOptions) -> Self {
+        Self {
+            out: String::new(),
+            ids: HashMap::new(),
+            escape_html: options.escape_html,
+        }
+    }
+
+    /// Close the open tag of a task as a task with the given state, followed by its checkbox.
+    fn checkbox(&mut self, state: TaskState) {
+        write!(
+            self,
+            " class=\"task {}\"><input type=\"checkbox\" disabled{}> ",
+            state_class(state),
+            if state == TaskState::Done {
+                " checked"
+            } else {
+                ""
+            }
+        );
+    }
+
+    /// A unique id for a heading, numbering repeated ids as `id-1`, `id-2` and so on.
+    fn heading_id(&mut self, inlines: &[Inline]) -> String {
+        let slug = match slug(&plain_text(inlines)) {
+            s if s.is_empty() => "section".to_string(),
+            s => s,
+        };
+        let count = self.ids.entry(slug.clone()).or_default();
+        *count += 1;
+        match *count {
+            1 => slug,
+            n => format!("{}-{}", slug, n - 1),
+        }
+    }
+
+    fn render_list(&mut self, list_type: ListType, items: &[ListItem]) {
+        let tag = match list_type {
+            ListType::Numbered(1, _) => {
+                self.out.push_str("<ol>\n");
+                "ol"
+            }
+            ListType::Numbered(start, _) => {
+                writeln!(self, "<ol start=\"{start}\">");
+                "ol"
+            }
+            ListType::Plain(_) => {
+                self.out.push_str("<ul>\n");
+                "ul"
+            }
+        };
+        for item in items {
+            self.visit_list_item(item);
+        }
+        writeln!(self, "</{tag}>");
+    }
+
+    fn render_table(
+        &mut self,
+        alignments: &[Alignment],
+        header: &[Vec<Inline>],
+        rows: &[TableRow],
+    ) {
+        let render_row = |r: &mut Self, row: &[Vec<Inline>], cell: &str| {
+            r.out.push_str("<tr>");
+            for (i, inlines) in row.iter().enumerate() {
+                let alignment = alignments.get(i).copied().unwrap_or(Alignment::None);
+                write!(r, "<{}{}>", cell, alignment_style(alignment));
+                r.visit_inlines(inlines);
+                write!(r, "</{cell}>");
+            }
+            r.out.push_str("</tr>\n");
+        };
+
+        self.out.push_str("<table>\n<thead>\n");
+        render_row(self, header, "th");
+        self.out.push_str("</thead>\n");
+        if !rows.is_empty() {
+            self.out.push_str("<tbody>\n");
+            for row in rows {
+                render_row(self, row, "td");
+            }
+            self.out.push_str("</tbody>\n");
+        }
+        self.out.push_str("</table>\n");
+    }
+
+    fn render_wrapped(&mut self, tag: &str, inlines: &[Inline]) {
+        write!(self, "<{tag}>");
+        self.visit_inlines(inlines);
+        write!(self, "</{tag}>");
+    }
+
+    fn render_link(&mut self, link: &Link) {
+        // a nodo shouldn't be able to run script in the page it is shown on, so such links are
+        // left as their text
+        if is_script_url(&link.destination) {
+            self.out.push_str(&escape(&link.text));
+            return;
+        }
+        let href = match link.link_type {
+            LinkType::Email => format!("mailto:{}", link.destination),
+            _ => link.destination.replace(' ', "%20"),
+        };
+        write!(self, "<a href=\"{}\"", escape(&href));
+        if !link.title.is_empty() {
+            write!(self, " title=\"{}\"", escape(&link.title));
+        }
+        write!(self, ">{}</a>", escape(&link.text));
+    }
+}
+
+impl<'a> Visit<'a> for Renderer {
+    fn visit_block(&mut self, b: &'a Block) {
+        match &b.kind {
+            BlockKind::Paragraph(inlines) => {
+                self.out.push_str("<p>");
+                self.visit_inlines(inlines);
+                self.out.push_str("</p>\n");
+            }
+            BlockKind::Heading(level, task, inlines) => {
+                let level = (*level).clamp(1, 6);
+                let id = self.heading_id(inlines);
+                write!(self, "<h{} id=\"{}\"", level, escape(&id));
+                match task {
+                    Some(state) => self.checkbox(*state),
+                    None => self.out.push('>'),
+                }
+                self.visit_inlines(inlines);
+                writeln!(self, "</h{level}>");
+            }
+            BlockKind::Code(lang, content) => {
+                self.out.push_str("<pre><code");
+                if let Some(lang) = lang.split_whitespace().next() {
+                    write!(self, " class=\"language-{}\"", escape(lang));
+                }
+                writeln!(self, ">{}</code></pre>", escape(content));
+            }
+            BlockKind::Quote(blocks) => {
+                self.out.push_str("<blockquote>\n");
+                self.visit_blocks(blocks);
+                self.out.push_str("</blockquote>\n");
+            }
+            BlockKind::List(list_type, items) => self.render_list(*list_type, items),
+            BlockKind::Table(alignments, header, rows) => {
+                self.render_table(alignments, header, rows);
+            }
+            BlockKind::FootnoteDefinition(label, blocks) => {
+                let label = escape(label);
+                writeln!(
+                    self,
+                    "<div class=\"footnote-definition\" id=\"fn-{label}\">\n<sup>{label}</sup>"
+                );
+                self.visit_blocks(blocks);
+                self.out.push_str("</div>\n");
+            }
+            BlockKind::Html(html) if self.escape_html => {
+                writeln!(self, "<pre>{}</pre>", escape(html));
+            }
+            BlockKind::Html(html) => {
+                self.out.push_str(html);
+                self.out.push('\n');
+            }
+            // the parser has already resolved reference links, so each `href` is complete
+            BlockKind::ReferenceDefinition(_, _, _) => {}
+            BlockKind::Rule => self.out.push_str("<hr>\n"),
+            BlockKind::Raw(s) => writeln!(self, "<pre>{}</pre>", escape(s)),
+        }
+    }
+
+    fn visit_list_item(&mut self, item: &'a ListItem) {
+        self.out.push_str("<li");
+        match item.task {
+            Some(state) => self.checkbox(state),
+            None => self.out.push('>'),
+        }
+
+        // a single paragraph is written without its own element, as in a tight list
+        let paragraphs = item
+            .blocks
+            .iter()
+            .filter(|b| matches!(b.kind, BlockKind::Paragraph(_)))
+            .count();
+        for (i, b) in item.blocks.iter().enumerate() {
+            match &b.kind {
+                BlockKind::Paragraph(inlines) if paragraphs == 1 => {
+                    self.visit_inlines(inlines);
+                    if i + 1 < item.blocks.len() {
+                        self.out.push('\n');
+                    }
+                }
+                _ => {
+                    if i == 0 {
+                        self.out.push('\n');
+                    }
+                    self.visit_block(b);
+                }
+            }
+        }
+        self.out.push_str("</li>\n");
+    }
+
+    fn visit_inline(&mut self, i: &'a Inline) {
+        match i {
+            Inline::Plain(s) | Inline::Raw(s) => self.out.push_str(&escape(s)),
+            Inline::Emph(inlines) => self.render_wrapped("em", inlines),
+            Inline::Strong(inlines) => self.render_wrapped("strong", inlines),
+            Inline::Strikethrough(inlines) => self.render_wrapped("del", inlines),
+            Inline::Code(s) => write!(self, "<code>{}</code>", escape(s)),
+            Inline::Link(link) => self.render_link(link),
+            Inline::Image(link) => {
+                write!(
+                    self,
+                    "<img src=\"{}\" alt=\"{}\"",
+                    escape(&link.destination.replace(' ', "%20")),
+                    escape(&link.text)
+                );
+                if !link.title.is_empty() {
+                    write!(self, " title=\"{}\"", escape(&link.title));
+                }
+                self.out.push('>');
+            }
+            Inline::Html(s) if self.escape_html => self.out.push_str(&escape(s)),
+            Inline::Html(s) => self.out.push_str(s),
+            Inline::FootnoteReference(label) => {
+                let label = escape(label);
+                write!(
+                    self,
+                    "<sup class=\"footnote-reference\"><a href=\"#fn-{label}\">{label}</a></sup>"
+                );
+            }
+            Inline::SoftBreak => self.out.push('\n'),
+            Inline::HardBreak => self.out.push_str("<br>\n"),
+        }
+    }
+}
+
+impl Render for Html {
+    type RenderError = RenderError;
+
+    fn render<W: std::io::Write>(n: &Nodo, w: &mut W) -> Result<(), Self::RenderError> {
+        Self::render_with(n, w, RenderOptions::default())
+    }
+}
+
+impl Html {
+    /// Render a nodo with the given options.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the html can't be written.
+    pub fn render_with<W: std::io::Write>(
+        n: &Nodo,
+        w: &mut W,
+        options: RenderOptions,
+    ) -> Result<(), RenderError> {
+        let mut renderer = Renderer::new(options);
+        renderer.visit_blocks(&n.blocks);
+        w.write_all(renderer.out.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Markdown, Parse};
+    use pretty_assertions::assert_eq;
+
+    fn render(md: &str) -> String {
+        let n = Markdown::parse(md).unwrap();
+        let mut out = Vec::new();
+        Html::render(&n, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn render_html() {
+        let md = "Some **bold**, *emph*, ~~gone~~ and `a<b>` with a [link](<a b> \"t\") and \
+                  ![alt](i.png)[^1].\\\nnext & <span>raw</span>
+
+- [x] done
+- [ ] open
+- [-] started
+  - nested
+
+3. three
+4. four
+
+> quoted
+
+```rust ignore
+fn main() {}
+```
+
+| a | b |
+|:--|--:|
+| 1 | 2 |
+
+---
+
+[^1]: Note.
+";
+        assert_eq!(
+            render(md),
+            r##"<p>Some <strong>bold</strong>, <em>emph</em>, <del>gone</del> and <code>a&lt;b&gt;</code> with a <a href="a%20b" title="t">link</a> and <img src="i.png" alt="alt"><sup class="footnote-reference"><a href="#fn-1">1</a></sup>.<br>
+next &amp; <span>raw</span></p>
+<ul>
+<li class="task done"><input type="checkbox" disabled checked> done</li>
+<li class="task open"><input type="checkbox" disabled> open</li>
+<li class="task in-progress"><input type="checkbox" disabled> started
+<ul>
+<li>nested</li>
+</ul>
+</li>
+</ul>
+<ol start="3">
+<li>three</li>
+<li>four</li>
+</ol>
+<blockquote>
+<p>quoted</p>
+</blockquote>
+<pre><code class="language-rust">fn main() {}
+</code></pre>
+<table>
+<thead>
+<tr><th style="text-align: left">a</th><th style="text-align: right">b</th></tr>
+</thead>
+<tbody>
+<tr><td style="text-align: left">1</td><td style="text-align: right">2</td></tr>
+</tbody>
+</table>
+<hr>
+<div class="footnote-definition" id="fn-1">
+<sup>1</sup>
+<p>Note.</p>
+</div>
+"##
+        );
+    }
+
+    #[test]
+    fn heading_ids() {
+        assert_eq!(
+            render("# Plans & ideas\n\n## Plans & ideas\n\n## Plans -- ideas\n\n## *Déjà* vu_2!\n\n## ?\n"),
+            r#"<h1 id="plans--ideas">Plans &amp; ideas</h1>
+<h2 id="plans--ideas-1">Plans &amp; ideas</h2>
+<h2 id="plans----ideas">Plans -- ideas</h2>
+<h2 id="déjà-vu_2"><em>Déjà</em> vu_2!</h2>
+<h2 id="section">?</h2>
+"#
+        );
+    }
+
+    #[test]
+    fn escape_raw_html() {
+        let n = Markdown::parse("<script>alert(1)</script>\n\nsome <b onclick=\"x\">bold</b>\n")
+            .unwrap();
+        let mut out = Vec::new();
+        Html::render_with(&n, &mut out, RenderOptions { escape_html: true }).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "<pre>&lt;script&gt;alert(1)&lt;/script&gt;</pre>
+<p>some &lt;b onclick=&quot;x&quot;&gt;bold&lt;/b&gt;</p>
+"
+        );
+    }
+
+    #[test]
+    fn script_links_are_text() {
+        assert_eq!(
+            render("[a](javascript:alert(1)) [b](<java\tscript:x>) [c](VBScript:x) [d](data:text/html,x) [e](notes.md)\n"),
+            "<p>a b c d <a href=\"notes.md\">e</a></p>\n"
+        );
+    }
+}
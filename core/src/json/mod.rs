@@ -0,0 +1,136 @@
+//! Nodos as json, serialising the nodo AST with serde so that other tools can read and write
+//! nodos in a structured form.
+//!
+//! Spans are included in the output but can be left out of the input, such as when a nodo is
+//! written by hand.
+
+use crate::{Nodo, Parse, Render};
+use std::io;
+use thiserror::Error;
+
+pub struct Json;
+
+#[derive(Error, Debug)]
+pub enum ParseError {
+    #[error("invalid json nodo: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum RenderError {
+    #[error("failed to write content: {0}")]
+    WriteFailure(#[from] io::Error),
+
+    #[error("failed to serialise nodo: {0}")]
+    Serialise(#[from] serde_json::Error),
+}
+
+impl Parse for Json {
+    type ParseError = ParseError;
+
+    fn parse(s: &str) -> Result<Nodo, Self::ParseError> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+impl Render for Json {
+    type RenderError = RenderError;
+
+    fn render<W: std::io::Write>(n: &Nodo, w: &mut W) -> Result<(), Self::RenderError> {
+        serde_json::to_writer_pretty(&mut *w, n)?;
+        writeln!(w)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockKind, Inline, ListItem, ListType, Markdown, TaskState};
+    use pretty_assertions::assert_eq;
+
+    fn render<R: Render>(n: &Nodo) -> String
+    where
+        R::RenderError: std::fmt::Debug,
+    {
+        let mut out = Vec::new();
+        R::render(n, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn markdown_round_trip() {
+        let md = "---
+title: Notes
+tags:
+  - work
+---
+
+# Plans
+
+Some **bold** and `code` with a [link](https://example.com \"title\")[^1].
+
+- [x] done
+- [-] started
+    1. nested
+
+> | a   |   b |
+> | :-- | --: |
+> | 1   |   2 |
+
+```rust
+fn main() {}
+```
+
+<!-- comment -->
+
+---
+
+[^1]: A footnote.
+";
+        let nodo = Markdown::parse(md).unwrap();
+        let json = render::<Json>(&nodo);
+        let parsed = Json::parse(&json).unwrap();
+
+        assert_eq!(parsed, nodo);
+        assert_eq!(parsed.blocks()[0].span(), nodo.blocks()[0].span());
+        assert_eq!(render::<Markdown>(&parsed), md);
+    }
+
+    #[test]
+    fn parse_without_spans() {
+        let json = r#"{
+            "metadata": null,
+            "blocks": [
+                {"kind": {"Heading": [1, null, [{"Plain": "Tasks"}]]}},
+                {"kind": {"List": [{"Plain": "-"}, [
+                    {"task": "Open", "blocks": [{"kind": {"Paragraph": [{"Plain": "write"}]}}]}
+                ]]}}
+            ]
+        }"#;
+        let nodo = Json::parse(json).unwrap();
+
+        assert_eq!(
+            nodo,
+            Nodo::new(
+                None,
+                vec![
+                    BlockKind::Heading(1, None, vec![Inline::Plain("Tasks".to_string())]).into(),
+                    BlockKind::List(
+                        ListType::Plain('-'),
+                        vec![ListItem::new(
+                            Some(TaskState::Open),
+                            vec![
+                                BlockKind::Paragraph(vec![Inline::Plain("write".to_string())])
+                                    .into()
+                            ]
+                        )]
+                    )
+                    .into(),
+                ]
+            )
+        );
+        assert_eq!(render::<Markdown>(&nodo), "# Tasks\n\n- [ ] write\n");
+        assert!(Json::parse("{\"blocks\": [{\"kind\": \"Heading\"}]}").is_err());
+    }
+}
@@ -1,9 +1,24 @@
 #![warn(clippy::pedantic, clippy::nursery)]
 // clippy::cargo
-mod markdown;
+pub mod html;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod markdown;
+pub mod metadata;
+pub mod mutate;
+pub mod org;
 pub mod query;
+pub mod registry;
+pub mod visit;
 
+pub use html::Html;
+#[cfg(feature = "serde")]
+pub use json::Json;
 pub use markdown::Markdown;
+pub use metadata::Metadata;
+pub use org::Org;
+pub use registry::Registry;
+use std::ops::Range;
 
 pub trait Parse {
     type ParseError;
@@ -29,43 +44,318 @@ pub trait Render {
     fn render<W: std::io::Write>(n: &Nodo, w: &mut W) -> Result<(), Self::RenderError>;
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum Inline {
+/// A renderer that builds up its output in a string.
+///
+/// Writing to a string can't fail, so this lets `write!` be used on the renderer without
+/// handling errors.
+trait StringRenderer {
+    fn out(&mut self) -> &mut String;
+
+    fn write_fmt(&mut self, args: std::fmt::Arguments) {
+        std::fmt::Write::write_fmt(self.out(), args).ok();
+    }
+}
+
+/// Inline content, found within paragraphs, headings and table cells.
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Inline {
     Plain(String),
     Emph(Vec<Inline>),
     Strong(Vec<Inline>),
     Code(String),
     Strikethrough(Vec<Inline>),
-    Link(String, String),
-    Image(String, String),
+    Link(Link),
+    /// An image, where the text of the link is the alt text.
+    Image(Link),
     Html(String),
+    /// A reference to the footnote with the given label.
+    FootnoteReference(String),
     SoftBreak,
     HardBreak,
+    /// Source that couldn't be parsed, kept verbatim by a lenient parse.
+    Raw(String),
+}
+
+/// How a link refers to its destination.
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LinkType {
+    /// `[text](destination)`
+    Inline,
+    /// `[text][label]`, with the label of the reference definition.
+    Reference(String),
+    /// `[text][]`, where the text is the label.
+    Collapsed,
+    /// `[text]`, where the text is the label.
+    Shortcut,
+    /// `<destination>`
+    Autolink,
+    /// `<address>` for an email address.
+    Email,
+}
+
+/// A link or image.
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Link {
+    pub link_type: LinkType,
+    pub text: String,
+    pub destination: String,
+    /// The title of the link, empty if it has none.
+    pub title: String,
+}
+
+/// A position in the source of a nodo.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    /// The byte offset from the start of the source.
+    pub offset: usize,
+    /// The line, counting from 1.
+    pub line: usize,
+    /// The column in characters, counting from 1.
+    pub column: usize,
+}
+
+/// The region of the source that a node was parsed from.
+///
+/// Nodes that weren't parsed from a source, such as those built programmatically, have the
+/// default span.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// The byte range of the source covered by this span.
+    #[must_use]
+    pub const fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
 }
 
-#[derive(Debug, Eq, PartialEq)]
-struct ListItem {
-    task: Option<bool>,
+/// The state of a task, written as the marker between the brackets of a task list item.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TaskState {
+    /// `[ ]`
+    Open,
+    /// `[x]`
+    Done,
+    /// `[-]`
+    InProgress,
+    /// `[~]`, not counted towards the progress of a nodo.
+    Cancelled,
+    /// `[>]`
+    Deferred,
+}
+
+impl TaskState {
+    /// The state written with the given marker, if it is one.
+    #[must_use]
+    pub const fn from_marker(c: char) -> Option<Self> {
+        match c {
+            ' ' => Some(Self::Open),
+            'x' | 'X' => Some(Self::Done),
+            '-' => Some(Self::InProgress),
+            '~' => Some(Self::Cancelled),
+            '>' => Some(Self::Deferred),
+            _ => None,
+        }
+    }
+
+    /// The marker to write between the brackets for this state.
+    #[must_use]
+    pub const fn marker(self) -> char {
+        match self {
+            Self::Open => ' ',
+            Self::Done => 'x',
+            Self::InProgress => '-',
+            Self::Cancelled => '~',
+            Self::Deferred => '>',
+        }
+    }
+}
+
+/// An item in a list, optionally marked as a task.
+///
+/// Equality ignores the span so that nodos can be compared by content.
+#[derive(Debug, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ListItem {
+    task: Option<TaskState>,
     blocks: Vec<Block>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    span: Span,
 }
 
+impl PartialEq for ListItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.task == other.task && self.blocks == other.blocks
+    }
+}
+
+impl ListItem {
+    #[must_use]
+    pub fn new(task: Option<TaskState>, blocks: Vec<Block>) -> Self {
+        Self {
+            task,
+            blocks,
+            span: Span::default(),
+        }
+    }
+
+    /// The state of this item if it is a task.
+    #[must_use]
+    pub const fn task(&self) -> Option<TaskState> {
+        self.task
+    }
+
+    /// Mark this item as a task with the given state, or as a plain item with `None`.
+    pub const fn set_task(&mut self, task: Option<TaskState>) {
+        self.task = task;
+    }
+
+    #[must_use]
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    #[must_use]
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// The kind of a list, along with the markers used to write it.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ListType {
+    /// A numbered list with the number of its first item and the delimiter after each number,
+    /// `.` or `)`.
+    Numbered(u64, char),
+    /// A bullet list with its bullet character, `-`, `*` or `+`.
+    Plain(char),
+}
+
+/// The alignment of a table column.
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
-enum ListType {
-    Numbered,
-    Plain,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+/// A row of table cells, each made up of inlines.
+pub type TableRow = Vec<Vec<Inline>>;
+
+/// Block level content of a nodo.
+///
+/// Equality ignores the span so that nodos can be compared by content.
+#[derive(Debug, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Block {
+    kind: BlockKind,
+    #[cfg_attr(feature = "serde", serde(default))]
+    span: Span,
+}
+
+impl PartialEq for Block {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl Block {
+    #[must_use]
+    pub fn new(kind: BlockKind) -> Self {
+        Self {
+            kind,
+            span: Span::default(),
+        }
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> &BlockKind {
+        &self.kind
+    }
+
+    pub const fn kind_mut(&mut self) -> &mut BlockKind {
+        &mut self.kind
+    }
+
+    #[must_use]
+    pub const fn span(&self) -> Span {
+        self.span
+    }
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum Block {
+impl From<BlockKind> for Block {
+    fn from(kind: BlockKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlockKind {
     Paragraph(Vec<Inline>),
-    Heading(u32, Vec<Inline>),
+    /// A heading with its level, starting at 1, and its state if the heading is a task, as org
+    /// headlines can be.
+    Heading(u32, Option<TaskState>, Vec<Inline>),
+    /// A code block with its language and content.
     Code(String, String),
     Quote(Vec<Block>),
     List(ListType, Vec<ListItem>),
+    /// A table with the alignment of each column, the header row and the body rows.
+    Table(Vec<Alignment>, TableRow, Vec<TableRow>),
+    /// The definition of the footnote with the given label.
+    FootnoteDefinition(String, Vec<Block>),
+    /// Raw html, such as a `<details>` element or a comment.
+    Html(String),
+    /// A link reference definition with its label, destination and title.
+    ReferenceDefinition(String, String, String),
     Rule,
+    /// Source that couldn't be parsed, kept verbatim by a lenient parse.
+    Raw(String),
 }
 
-#[derive(Default, Debug, Eq, PartialEq)]
+#[derive(Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Nodo {
+    metadata: Option<Metadata>,
     blocks: Vec<Block>,
 }
+
+impl Nodo {
+    #[must_use]
+    pub const fn new(metadata: Option<Metadata>, blocks: Vec<Block>) -> Self {
+        Self { metadata, blocks }
+    }
+
+    /// The top level blocks of this nodo.
+    #[must_use]
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// The metadata from the front matter of this nodo, if it had any.
+    #[must_use]
+    pub const fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_ref()
+    }
+
+    pub const fn metadata_mut(&mut self) -> Option<&mut Metadata> {
+        self.metadata.as_mut()
+    }
+
+    /// Replace the metadata of this nodo, returning the old metadata.
+    pub const fn set_metadata(&mut self, metadata: Option<Metadata>) -> Option<Metadata> {
+        std::mem::replace(&mut self.metadata, metadata)
+    }
+}
@@ -1,6 +1,11 @@
-use crate::{Block, Inline, ListItem, ListType, Nodo, Parse, Render};
-use pulldown_cmark::{Event, Options, Parser, Tag};
-use std::{io, iter::Peekable};
+use crate::{
+    metadata,
+    visit::{walk_inline, walk_list_item, Visit},
+    Alignment, Block, BlockKind, Inline, Link, LinkType, ListItem, ListType, Metadata, Nodo, Parse,
+    Position, Render, Span, TableRow, TaskState,
+};
+use pulldown_cmark::{CowStr, Event, OffsetIter, Options, Parser, Tag};
+use std::{fmt, io, iter::Peekable, ops::Range};
 use thiserror::Error;
 
 #[cfg(not(test))]
@@ -11,92 +16,593 @@ use std::println as trace;
 
 pub struct Markdown;
 
+/// Options controlling how markdown is parsed.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct ParseOptions {
+    /// Keep any source that can't be parsed as raw blocks and inlines rather than failing.
+    pub lenient: bool,
+}
+
+/// Where in the source a parse error occurred.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Location {
+    /// The line, counting from 1.
+    pub line: usize,
+    /// The column in characters, counting from 1.
+    pub column: usize,
+    /// The source line that the error occurred on.
+    pub snippet: String,
+    /// The number of characters of the snippet to underline, starting at the column.
+    pub width: usize,
+}
+
+/// Renders the snippet with the error underlined, in the style of a compiler diagnostic.
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let gutter = " ".repeat(self.line.to_string().len());
+        // keep tabs so that the carets line up with the snippet
+        let indent = self
+            .snippet
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        writeln!(f, "{}--> {}:{}", gutter, self.line, self.column)?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{} | {}", self.line, self.snippet)?;
+        write!(f, "{} | {}{}", gutter, indent, "^".repeat(self.width))
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ParseError {
-    #[error("found an unexpected event: {event}")]
-    UnexpectedElement { event: String },
+    #[error("found an unexpected event: {event}\n{location}")]
+    UnexpectedElement { event: String, location: Location },
 
-    #[error("received non-text event while parsing plaintext: {event}")]
-    NoText { event: String },
+    #[error("received non-text event while parsing plaintext: {event}\n{location}")]
+    NoText { event: String, location: Location },
 
-    #[error("found non-end event while parsing plaintext: {event}")]
-    ExpectedEnd { event: String },
+    #[error("failed to parse link reference definition\n{location}")]
+    InvalidReferenceDefinition { location: Location },
+
+    #[error("failed to parse front matter: {0}")]
+    InvalidFrontMatter(#[from] metadata::Error),
 }
 
 #[derive(Error, Debug)]
 pub enum RenderError {
     #[error("failed to write content: {0}")]
     WriteFailure(#[from] io::Error),
+
+    #[error("failed to render front matter: {0}")]
+    InvalidFrontMatter(#[from] metadata::Error),
 }
 
 const INDENT: &str = "    ";
 
-fn parse_blocks(p: &mut Peekable<Parser>) -> Result<Vec<Block>, ParseError> {
+/// The events of a markdown document, keeping track of where in the source each came from.
+struct Events<'a> {
+    inner: Peekable<OffsetIter<'a>>,
+    source: &'a str,
+    /// The byte offset of each line start in the source.
+    line_starts: Vec<usize>,
+    /// The offset of the parsed content in the source, after any front matter.
+    offset: usize,
+    /// The source range of the last event returned from `next`.
+    range: Range<usize>,
+    lenient: bool,
+}
+
+impl<'a> Events<'a> {
+    fn new(source: &'a str, content: &'a str, opts: Options, lenient: bool) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            inner: Parser::new_ext(content, opts).into_offset_iter().peekable(),
+            source,
+            line_starts,
+            offset: source.len() - content.len(),
+            range: 0..0,
+            lenient,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&Event<'a>> {
+        self.inner.peek().map(|(e, _)| e)
+    }
+
+    /// The next event if it continues the html block of the last event, rather than starting
+    /// another html block after a blank line.
+    fn peek_html_line(&mut self) -> Option<CowStr<'a>> {
+        let gap = self.offset + self.range.end;
+        let source = self.source;
+        match self.inner.peek() {
+            Some((Event::Html(s), r)) if !source[gap..self.offset + r.start].contains('\n') => {
+                Some(s.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// The position of a byte offset into the parsed content.
+    fn position(&self, offset: usize) -> Position {
+        let offset = self.offset + offset;
+        let line = self.line_starts.partition_point(|&s| s <= offset);
+        let line_start = self.line_starts[line - 1];
+        Position {
+            offset,
+            line,
+            column: self.source[line_start..offset].chars().count() + 1,
+        }
+    }
+
+    /// The location of the last event returned from `next`, for reporting errors.
+    fn location(&self) -> Location {
+        self.location_of(self.range.clone())
+    }
+
+    fn location_of(&self, range: Range<usize>) -> Location {
+        let start = self.position(range.start);
+        let line_start = self.line_starts[start.line - 1];
+        let line_end = self.source[line_start..]
+            .find('\n')
+            .map_or(self.source.len(), |i| line_start + i);
+        let end = (self.offset + range.end).clamp(start.offset, line_end);
+        Location {
+            line: start.line,
+            column: start.column,
+            snippet: self.source[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+            width: self.source[start.offset..end].chars().count().max(1),
+        }
+    }
+
+    /// Skip the rest of the element covering `range` and return its source.
+    fn raw(&mut self, range: Range<usize>) -> String {
+        // end events cover their whole element, so check both ends to stay within this one
+        while self
+            .inner
+            .peek()
+            .is_some_and(|(_, r)| range.contains(&r.start) && r.end <= range.end)
+        {
+            self.next();
+        }
+        self.source_of(range).to_string()
+    }
+
+    /// The link reference definitions in a range of the parsed content.
+    ///
+    /// Definitions don't produce any events, so they are found in the source between blocks.
+    /// When lenient, a definition that can't be parsed is kept as a raw block.
+    fn reference_definitions(&self, range: Range<usize>) -> Result<Vec<Block>, ParseError> {
+        let mut blocks = Vec::new();
+        if range.is_empty() {
+            return Ok(blocks);
+        }
+
+        // the source without the markers of the blocks it's in, along with where each of its
+        // lines starts in the text and in the parsed content
+        let mut text = String::new();
+        let mut lines = Vec::new();
+        let mut offset = range.start;
+        for (i, line) in self.source_of(range).split_inclusive('\n').enumerate() {
+            let content = strip_markers(line, i == 0);
+            lines.push((text.len(), offset + line.len() - content.len()));
+            text.push_str(content);
+            offset += line.len();
+        }
+        let content_range = |r: Range<usize>| {
+            let offset = |i: usize| {
+                let (start, offset) = lines[lines.partition_point(|&(s, _)| s <= i) - 1];
+                offset + i - start
+            };
+            offset(r.start)..offset(r.end - 1) + 1
+        };
+
+        let mut start = 0;
+        // the start of source that looks like a definition but couldn't be parsed
+        let mut unparsed = None;
+        while start < text.len() {
+            let rest = &text[start..];
+            let line = rest.split_inclusive('\n').next().unwrap_or(rest);
+            let parsed = parse_reference_definition(rest);
+            if let Some(raw) = unparsed.filter(|_| parsed.is_some() || line.trim().is_empty()) {
+                blocks
+                    .push(self.unparsed_definition(content_range(raw..start), &text[raw..start])?);
+                unparsed = None;
+            }
+
+            if let Some((kind, len)) = parsed {
+                let range = content_range(start..start + len);
+                let span = Span {
+                    start: self.position(range.start),
+                    end: self.position(range.end),
+                };
+                blocks.push(Block { kind, span });
+                start += len;
+            } else {
+                // the label of a definition can run over several lines
+                let footnote = rest.starts_with("[^");
+                if unparsed.is_none() && rest.starts_with('[') && rest.contains("]:") && !footnote {
+                    unparsed = Some(start);
+                }
+                start += line.len();
+            }
+        }
+        if let Some(raw) = unparsed {
+            blocks.push(self.unparsed_definition(content_range(raw..text.len()), &text[raw..])?);
+        }
+        Ok(blocks)
+    }
+
+    /// Handle a definition that can't be parsed, keeping its text as a raw block when lenient.
+    fn unparsed_definition(&self, range: Range<usize>, text: &str) -> Result<Block, ParseError> {
+        if !self.lenient {
+            return Err(ParseError::InvalidReferenceDefinition {
+                location: self.location_of(range),
+            });
+        }
+        Ok(Block {
+            kind: raw_block(text.to_string()),
+            span: Span {
+                start: self.position(range.start),
+                end: self.position(range.end),
+            },
+        })
+    }
+
+    /// The source covered by a range of the parsed content.
+    fn source_of(&self, range: Range<usize>) -> &'a str {
+        &self.source[self.offset + range.start..self.offset + range.end]
+    }
+
+    /// Handle an event that can't be parsed in the current context, returning its source when
+    /// lenient.
+    fn unexpected(&mut self, e: &Event) -> Result<String, ParseError> {
+        if self.lenient {
+            Ok(self.raw(self.range.clone()))
+        } else {
+            Err(unexpected_element(e, self.location()))
+        }
+    }
+
+    /// When lenient, replace a failure to parse the element covering `range` with its source.
+    fn recover<T>(
+        &mut self,
+        range: Range<usize>,
+        result: Result<T, ParseError>,
+        raw: impl FnOnce(String) -> T,
+    ) -> Result<T, ParseError> {
+        match result {
+            Err(_) if self.lenient => Ok(raw(self.raw(range))),
+            result => result,
+        }
+    }
+
+    /// The span of the last event returned from `next`.
+    fn span(&self) -> Span {
+        Span {
+            start: self.position(self.range.start),
+            end: self.position(self.range.end),
+        }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (e, range) = self.inner.next()?;
+        self.range = range;
+        Some(e)
+    }
+}
+
+fn unexpected_element(e: &Event, location: Location) -> ParseError {
+    ParseError::UnexpectedElement {
+        event: format!("{e:?}"),
+        location,
+    }
+}
+
+fn parse_blocks(p: &mut Events) -> Result<Vec<Block>, ParseError> {
     let mut blocks = Vec::new();
+    // the start of the source not yet covered by a block
+    let mut covered = p.range.start;
 
-    while let Some(e) = p.next() {
+    loop {
+        let Some(e) = p.next() else {
+            let end = p.source.len() - p.offset;
+            blocks.append(&mut p.reference_definitions(covered..end)?);
+            break;
+        };
         trace!("parse_blocks: {:?}", e);
 
-        match e {
+        // end events cover their whole element, so the uncovered source runs up to its end
+        let uncovered = match e {
+            Event::End(_) => covered..p.range.end,
+            _ => covered..p.range.start,
+        };
+        blocks.append(&mut p.reference_definitions(uncovered)?);
+
+        // the span of a start event covers the whole element
+        let mut span = p.span();
+        let range = p.range.clone();
+        let kind = match e {
             Event::Start(ref tag) => match tag {
-                Tag::Heading(level) => blocks.push(Block::Heading(*level, parse_inlines(p)?)),
-                Tag::Paragraph => blocks.push(Block::Paragraph(parse_inlines(p)?)),
-                Tag::BlockQuote => blocks.push(Block::Quote(parse_blocks(p)?)),
+                Tag::Heading(level) => {
+                    let mut inlines = parse_inlines(p)?;
+                    // markdown has no heading tasks, so they are written like task list items
+                    let content = p.source_of(range.clone()).trim_start_matches('#');
+                    let task =
+                        strip_task_marker(content.trim_start_matches([' ', '\t']), &mut inlines);
+                    BlockKind::Heading(*level, task, inlines)
+                }
+                Tag::Paragraph => BlockKind::Paragraph(parse_inlines(p)?),
+                Tag::BlockQuote => BlockKind::Quote(parse_blocks(p)?),
                 Tag::CodeBlock(kind) => {
                     let lang = match kind {
                         pulldown_cmark::CodeBlockKind::Indented => String::new(),
                         pulldown_cmark::CodeBlockKind::Fenced(lang) => lang.to_string(),
                     };
-                    blocks.push(Block::Code(lang, parse_text(p)?))
+                    let content = parse_text(p).map(|c| BlockKind::Code(lang, c));
+                    p.recover(range, content, raw_block)?
                 }
                 Tag::List(kind) => {
-                    if kind.is_some() {
-                        blocks.push(Block::List(ListType::Numbered, parse_list_items(p)?))
-                    } else {
-                        blocks.push(Block::List(ListType::Plain, parse_list_items(p)?))
-                    }
+                    // the markers aren't part of the events, so read them from the source
+                    let ty = list_type(*kind, p.source_of(range.clone()));
+                    let items = parse_list_items(p).map(|items| BlockKind::List(ty, items));
+                    p.recover(range, items, raw_block)?
                 }
-                Tag::Item
-                | Tag::FootnoteDefinition(_)
-                | Tag::TableHead
-                | Tag::TableRow
-                | Tag::TableCell
-                | Tag::Table(_)
-                | Tag::Emphasis
+                Tag::Table(alignments) => {
+                    let alignments = alignments.iter().map(|a| convert_alignment(*a)).collect();
+                    let table = parse_table(p)
+                        .map(|(header, rows)| BlockKind::Table(alignments, header, rows));
+                    p.recover(range, table, raw_block)?
+                }
+                Tag::FootnoteDefinition(label) => {
+                    BlockKind::FootnoteDefinition(label.to_string(), parse_blocks(p)?)
+                }
+                Tag::Item | Tag::TableHead | Tag::TableRow | Tag::TableCell => {
+                    raw_block(p.unexpected(&e)?)
+                }
+                Tag::Emphasis
                 | Tag::Strikethrough
                 | Tag::Strong
                 | Tag::Link(_, _, _)
                 | Tag::Image(_, _, _) => {
-                    return Err(ParseError::UnexpectedElement {
-                        event: format!("{:?}", e),
-                    })
+                    let mut text = vec![parse_tight_inline(p, e)?];
+                    for inline in parse_tight_paragraph(p)? {
+                        push_inline(&mut text, inline);
+                    }
+                    span.end = p.span().end;
+                    BlockKind::Paragraph(text)
                 }
             },
             Event::End(_) => break,
-            Event::Text(s) => {
-                let mut text = vec![Inline::Plain(s.to_string())];
-                let mut inlines = parse_tight_paragraph(p)?;
-                text.append(&mut inlines);
-                blocks.push(Block::Paragraph(text))
+            // block html ends its line, unlike html at the start of a tight paragraph
+            Event::Html(ref s) if s.ends_with('\n') || p.peek().is_none() => {
+                let mut html = s.to_string();
+                // the indent of the first line isn't part of its event but it is kept on the
+                // following lines, so remove it from those to keep the block consistent
+                let column = p.span().start.column;
+                // html blocks are split into an event for each line
+                while let Some(s) = p.peek_html_line() {
+                    p.next();
+                    let indent = column.saturating_sub(p.span().start.column);
+                    let trimmed = s.trim_start_matches(' ');
+                    html.push_str(&s[(s.len() - trimmed.len()).min(indent)..]);
+                }
+                span.end = p.span().end;
+                html.truncate(html.trim_end_matches(['\r', '\n']).len());
+                BlockKind::Html(html)
             }
-            Event::Code(_)
-            | Event::Html(_)
-            | Event::FootnoteReference(_)
-            | Event::HardBreak
-            | Event::SoftBreak
-            | Event::TaskListMarker(_) => {
-                return Err(ParseError::UnexpectedElement {
-                    event: format!("{:?}", e),
-                })
+            Event::Text(_) | Event::Code(_) | Event::Html(_) | Event::FootnoteReference(_) => {
+                let mut text = vec![parse_tight_inline(p, e)?];
+                for inline in parse_tight_paragraph(p)? {
+                    push_inline(&mut text, inline);
+                }
+                span.end = p.span().end;
+                BlockKind::Paragraph(text)
             }
-            Event::Rule => blocks.push(Block::Rule),
-        }
+            Event::HardBreak | Event::SoftBreak | Event::TaskListMarker(_) => {
+                raw_block(p.unexpected(&e)?)
+            }
+            Event::Rule => BlockKind::Rule,
+        };
+        blocks.push(Block { kind, span });
+        covered = p.range.end;
     }
 
     Ok(blocks)
 }
 
-fn parse_list_items(p: &mut Peekable<Parser>) -> Result<Vec<ListItem>, ParseError> {
+/// The content of a line of source without the markers of the quotes it's in, or of the list
+/// items it starts when it's the `first` line of a block.
+fn strip_markers(line: &str, first: bool) -> &str {
+    fn quote(s: &str) -> &str {
+        s.trim_start_matches(['>', ' ', '\t'])
+    }
+
+    fn item(s: &str) -> Option<&str> {
+        let rest = s.strip_prefix(['-', '*', '+']).or_else(|| {
+            let number = s.trim_start_matches(|c: char| c.is_ascii_digit());
+            number
+                .strip_prefix(['.', ')'])
+                .filter(|_| number.len() < s.len())
+        })?;
+        rest.starts_with(char::is_whitespace).then_some(rest)
+    }
+
+    let mut line = quote(line);
+    if first {
+        while let Some(rest) = item(line) {
+            line = quote(rest);
+        }
+    }
+    line
+}
+
+/// Parse a link reference definition, `[label]: destination "title"`, from the start of some
+/// source without block markers, returning it along with the length of source it covers.
+///
+/// The destination and title can each start on the next line, and the title can run over
+/// several lines.
+fn parse_reference_definition(s: &str) -> Option<(BlockKind, usize)> {
+    let (label, rest) = s.strip_prefix('[')?.split_once("]:")?;
+    // the start of a footnote definition
+    if label.starts_with('^') || label.contains('\n') {
+        return None;
+    }
+
+    let rest = skip_whitespace(rest);
+    let (destination, rest) = match rest.strip_prefix('<') {
+        Some(rest) => rest.split_once('>')?,
+        None => match rest.split_at(rest.find(char::is_whitespace).unwrap_or(rest.len())) {
+            ("", _) => return None,
+            split => split,
+        },
+    };
+    if destination.contains('\n') {
+        return None;
+    }
+
+    // a title has to end its line, otherwise the definition ends with the destination
+    let (title, rest) = parse_title(rest)
+        .and_then(|(title, rest)| Some((title, line_end(rest)?)))
+        .or_else(|| Some(("", line_end(rest)?)))?;
+    Some((
+        BlockKind::ReferenceDefinition(label.to_string(), unescape(destination), unescape(title)),
+        s.len() - rest.len(),
+    ))
+}
+
+/// Parse the title of a link reference definition, separated from its destination by
+/// whitespace, returning the title and the source after it.
+fn parse_title(s: &str) -> Option<(&str, &str)> {
+    let rest = skip_whitespace(s);
+    if rest.len() == s.len() {
+        return None;
+    }
+    let close = match rest.chars().next()? {
+        '"' => '"',
+        '\'' => '\'',
+        '(' => ')',
+        _ => return None,
+    };
+    let mut escaped = false;
+    let end = rest[1..].find(|c: char| {
+        let end = c == close && !escaped;
+        escaped = c == '\\' && !escaped;
+        end
+    })? + 1;
+    let title = &rest[1..end];
+    // a blank line ends the definition before the title does
+    if title.lines().skip(1).any(|l| l.trim().is_empty()) {
+        return None;
+    }
+    Some((title, &rest[end + 1..]))
+}
+
+/// Skip spaces and tabs, including at most one line ending.
+fn skip_whitespace(s: &str) -> &str {
+    let s = s.trim_start_matches([' ', '\t']);
+    s.strip_prefix("\r\n")
+        .or_else(|| s.strip_prefix('\n'))
+        .unwrap_or(s)
+        .trim_start_matches([' ', '\t'])
+}
+
+/// The source after the end of the current line, if there is nothing else on it.
+fn line_end(s: &str) -> Option<&str> {
+    let s = s.trim_start_matches([' ', '\t']);
+    if s.is_empty() {
+        return Some(s);
+    }
+    s.strip_prefix("\r\n").or_else(|| s.strip_prefix('\n'))
+}
+
+/// Remove the backslash escapes from punctuation.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(&next) if c == '\\' && next.is_ascii_punctuation() => {
+                out.push(next);
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn raw_block(mut s: String) -> BlockKind {
+    // the separation between blocks is added back when rendering
+    s.truncate(s.trim_end_matches(['\r', '\n']).len());
+    BlockKind::Raw(s)
+}
+
+/// The type of a list that starts with `marker`, numbered if it has a `start`.
+fn list_type(start: Option<u64>, marker: &str) -> ListType {
+    let marker = marker.trim_start();
+    start.map_or_else(
+        || ListType::Plain(marker.chars().next().unwrap_or('-')),
+        |start| {
+            let delimiter = marker.trim_start_matches(|c: char| c.is_ascii_digit());
+            ListType::Numbered(start, delimiter.chars().next().unwrap_or('.'))
+        },
+    )
+}
+
+const fn convert_alignment(a: pulldown_cmark::Alignment) -> Alignment {
+    match a {
+        pulldown_cmark::Alignment::None => Alignment::None,
+        pulldown_cmark::Alignment::Left => Alignment::Left,
+        pulldown_cmark::Alignment::Center => Alignment::Center,
+        pulldown_cmark::Alignment::Right => Alignment::Right,
+    }
+}
+
+fn parse_table(p: &mut Events) -> Result<(TableRow, Vec<TableRow>), ParseError> {
+    let mut header = Vec::new();
+    let mut rows = Vec::new();
+
+    while let Some(e) = p.next() {
+        trace!("parse_table: {:?}", e);
+
+        match e {
+            Event::Start(Tag::TableHead) => header = parse_table_row(p)?,
+            Event::Start(Tag::TableRow) => rows.push(parse_table_row(p)?),
+            Event::End(_) => break,
+            _ => return Err(unexpected_element(&e, p.location())),
+        }
+    }
+
+    Ok((header, rows))
+}
+
+fn parse_table_row(p: &mut Events) -> Result<Vec<Vec<Inline>>, ParseError> {
+    let mut cells = Vec::new();
+
+    while let Some(e) = p.next() {
+        trace!("parse_table_row: {:?}", e);
+
+        match e {
+            Event::Start(Tag::TableCell) => cells.push(parse_inlines(p)?),
+            Event::End(_) => break,
+            _ => return Err(unexpected_element(&e, p.location())),
+        }
+    }
+
+    Ok(cells)
+}
+
+fn parse_list_items(p: &mut Events) -> Result<Vec<ListItem>, ParseError> {
     let mut items = Vec::new();
 
     while let Some(e) = p.next() {
@@ -118,26 +624,30 @@ fn parse_list_items(p: &mut Peekable<Parser>) -> Result<Vec<ListItem>, ParseErro
                 | Tag::Strong
                 | Tag::Strikethrough
                 | Tag::Link(_, _, _)
-                | Tag::Image(_, _, _) => {
-                    return Err(ParseError::UnexpectedElement {
-                        event: format!("{:?}", e),
-                    })
-                }
-                Tag::Item => match p.peek() {
-                    None => break,
-                    Some(Event::TaskListMarker(b)) => {
-                        let b = *b;
-                        p.next().unwrap();
-                        items.push(ListItem {
-                            task: Some(b),
-                            blocks: parse_blocks(p)?,
-                        })
+                | Tag::Image(_, _, _) => return Err(unexpected_element(&e, p.location())),
+                Tag::Item => {
+                    let span = p.span();
+                    match p.peek() {
+                        None => break,
+                        Some(Event::TaskListMarker(b)) => {
+                            let task = if *b { TaskState::Done } else { TaskState::Open };
+                            p.next().unwrap();
+                            items.push(ListItem {
+                                task: Some(task),
+                                blocks: parse_blocks(p)?,
+                                span,
+                            });
+                        }
+                        Some(_) => {
+                            let mut blocks = parse_blocks(p)?;
+                            items.push(ListItem {
+                                task: task_marker(p.source, &mut blocks),
+                                blocks,
+                                span,
+                            });
+                        }
                     }
-                    Some(_) => items.push(ListItem {
-                        task: None,
-                        blocks: parse_blocks(p)?,
-                    }),
-                },
+                }
             },
             Event::End(_) => break,
             Event::Text(_)
@@ -146,16 +656,14 @@ fn parse_list_items(p: &mut Peekable<Parser>) -> Result<Vec<ListItem>, ParseErro
             | Event::SoftBreak
             | Event::HardBreak => items.push(ListItem {
                 task: None,
+                span: p.span(),
                 blocks: parse_blocks(p)?,
             }),
-            Event::FootnoteReference(_) => {
-                return Err(ParseError::UnexpectedElement {
-                    event: format!("{:?}", e),
-                })
-            }
+            Event::FootnoteReference(_) => return Err(unexpected_element(&e, p.location())),
             Event::Rule => continue,
             Event::TaskListMarker(b) => items.push(ListItem {
-                task: Some(b),
+                task: Some(if b { TaskState::Done } else { TaskState::Open }),
+                span: p.span(),
                 blocks: parse_blocks(p)?,
             }),
         }
@@ -164,7 +672,59 @@ fn parse_list_items(p: &mut Peekable<Parser>) -> Result<Vec<ListItem>, ParseErro
     Ok(items)
 }
 
-fn parse_tight_paragraph(p: &mut Peekable<Parser>) -> Result<Vec<Inline>, ParseError> {
+/// Take the marker of a task state that the parser doesn't know about, such as `[-]`, from the
+/// start of the first paragraph of an item.
+///
+/// The source is checked so that an escaped `\[-]` stays as text.
+fn task_marker(source: &str, blocks: &mut [Block]) -> Option<TaskState> {
+    let first = blocks.first_mut()?;
+    let (before, marker) = source.split_at(first.span.start.offset);
+    if before.ends_with('\\') {
+        return None;
+    }
+    let BlockKind::Paragraph(inlines) = &mut first.kind else {
+        return None;
+    };
+    strip_task_marker(marker, inlines)
+}
+
+/// Remove a task marker from the start of some inlines, given the source they start at, and
+/// return its state.
+fn strip_task_marker(marker: &str, inlines: &mut Vec<Inline>) -> Option<TaskState> {
+    let Some(Inline::Plain(text)) = inlines.first_mut() else {
+        return None;
+    };
+
+    let mut chars = marker.chars();
+    let (Some('['), Some(c), Some(']'), Some(' ' | '\t')) =
+        (chars.next(), chars.next(), chars.next(), chars.next())
+    else {
+        return None;
+    };
+    let state = TaskState::from_marker(c)?;
+    if !text.starts_with(&marker[..3]) {
+        return None;
+    }
+
+    let rest = text[3..].trim_start_matches([' ', '\t']).to_string();
+    if rest.is_empty() {
+        inlines.remove(0);
+    } else {
+        *text = rest;
+    }
+    Some(state)
+}
+
+/// Add an inline, merging adjacent text that the parser splits at escapes and entities.
+fn push_inline(inlines: &mut Vec<Inline>, inline: Inline) {
+    if let (Some(Inline::Plain(last)), Inline::Plain(s)) = (inlines.last_mut(), &inline) {
+        last.push_str(s);
+    } else {
+        inlines.push(inline);
+    }
+}
+
+fn parse_tight_paragraph(p: &mut Events) -> Result<Vec<Inline>, ParseError> {
     let mut inlines = Vec::new();
 
     while let Some(e) = p.peek() {
@@ -183,27 +743,13 @@ fn parse_tight_paragraph(p: &mut Peekable<Parser>) -> Result<Vec<Inline>, ParseE
                 | Tag::TableRow
                 | Tag::TableCell
                 | Tag::Item => break,
-                Tag::Emphasis => {
-                    p.next().unwrap();
-                    inlines.push(Inline::Emph(parse_tight_paragraph(p)?))
-                }
-                Tag::Strong => {
-                    p.next().unwrap();
-                    inlines.push(Inline::Strong(parse_tight_paragraph(p)?))
-                }
-                Tag::Strikethrough => {
-                    p.next().unwrap();
-                    inlines.push(Inline::Strikethrough(parse_tight_paragraph(p)?))
-                }
-                Tag::Link(_type, s, l) => {
-                    let (s, l) = (s.to_string(), l.to_string());
-                    p.next().unwrap();
-                    inlines.push(Inline::Link(s, l))
-                }
-                Tag::Image(_type, s, l) => {
-                    let (s, l) = (s.to_string(), l.to_string());
-                    p.next().unwrap();
-                    inlines.push(Inline::Image(s, l))
+                Tag::Emphasis
+                | Tag::Strong
+                | Tag::Strikethrough
+                | Tag::Link(_, _, _)
+                | Tag::Image(_, _, _) => {
+                    let e = p.next().unwrap();
+                    push_inline(&mut inlines, parse_tight_inline(p, e)?);
                 }
             },
             Event::End(tag) => match tag {
@@ -227,37 +773,30 @@ fn parse_tight_paragraph(p: &mut Peekable<Parser>) -> Result<Vec<Inline>, ParseE
                 | Tag::TableRow
                 | Tag::TableCell => break,
             },
-            Event::Text(s) => {
-                let s = s.to_string();
-                p.next().unwrap();
-                inlines.push(Inline::Plain(s))
-            }
-            Event::Code(s) => {
-                let s = s.to_string();
-                p.next().unwrap();
-                inlines.push(Inline::Code(s))
+            Event::Text(_) | Event::Code(_) | Event::FootnoteReference(_) => {
+                let e = p.next().unwrap();
+                push_inline(&mut inlines, parse_tight_inline(p, e)?);
             }
             Event::Html(s) => {
                 let s = s.to_string();
                 p.next().unwrap();
-                inlines.push(Inline::Html(s))
+                push_inline(&mut inlines, Inline::Html(s));
             }
             Event::SoftBreak => {
                 p.next().unwrap();
-                inlines.push(Inline::SoftBreak)
+                push_inline(&mut inlines, Inline::SoftBreak);
             }
             Event::HardBreak => {
                 p.next().unwrap();
-                inlines.push(Inline::HardBreak)
+                push_inline(&mut inlines, Inline::HardBreak);
             }
             Event::Rule => {
                 p.next().unwrap();
                 continue;
             }
-            Event::FootnoteReference(_) | Event::TaskListMarker(_) => {
-                return Err(ParseError::UnexpectedElement {
-                    event: format!("{:?}", e),
-                })
+            Event::TaskListMarker(_) => {
+                let e = p.next().unwrap();
+                push_inline(&mut inlines, Inline::Raw(p.unexpected(&e)?));
             }
         }
     }
@@ -265,7 +804,28 @@ fn parse_tight_paragraph(p: &mut Peekable<Parser>) -> Result<Vec<Inline>, ParseE
     Ok(inlines)
 }
 
-fn parse_inlines(p: &mut Peekable<Parser>) -> Result<Vec<Inline>, ParseError> {
+/// Parse the inline started by `e` in a tight paragraph, where the inline elements aren't
+/// wrapped in a paragraph.
+fn parse_tight_inline(p: &mut Events, e: Event) -> Result<Inline, ParseError> {
+    Ok(match e {
+        Event::Text(s) => Inline::Plain(s.to_string()),
+        Event::Code(s) => Inline::Code(s.to_string()),
+        Event::Html(s) => Inline::Html(s.to_string()),
+        Event::FootnoteReference(s) => Inline::FootnoteReference(s.to_string()),
+        Event::Start(Tag::Emphasis) => Inline::Emph(parse_tight_paragraph(p)?),
+        Event::Start(Tag::Strong) => Inline::Strong(parse_tight_paragraph(p)?),
+        Event::Start(Tag::Strikethrough) => Inline::Strikethrough(parse_tight_paragraph(p)?),
+        Event::Start(Tag::Link(ty, destination, title)) => {
+            parse_link(p, ty, &destination, &title, Inline::Link)?
+        }
+        Event::Start(Tag::Image(ty, destination, title)) => {
+            parse_link(p, ty, &destination, &title, Inline::Image)?
+        }
+        _ => Inline::Raw(p.unexpected(&e)?),
+    })
+}
+
+fn parse_inlines(p: &mut Events) -> Result<Vec<Inline>, ParseError> {
     let mut inlines = Vec::new();
 
     while let Some(e) = p.next() {
@@ -283,198 +843,638 @@ fn parse_inlines(p: &mut Peekable<Parser>) -> Result<Vec<Inline>, ParseError> {
                 | Tag::TableHead
                 | Tag::TableRow
                 | Tag::TableCell
-                | Tag::Item => {
-                    return Err(ParseError::UnexpectedElement {
-                        event: format!("{:?}", e),
-                    })
-                }
-                Tag::Emphasis => inlines.push(Inline::Emph(parse_inlines(p)?)),
-                Tag::Strong => inlines.push(Inline::Strong(parse_inlines(p)?)),
-                Tag::Strikethrough => inlines.push(Inline::Strikethrough(parse_inlines(p)?)),
-                Tag::Link(_type, l, s) => {
-                    inlines.push(Inline::Link(s.to_string() + &parse_text(p)?, l.to_string()))
-                }
-                Tag::Image(_type, l, s) => {
-                    inlines.push(Inline::Image(s.to_string(), l.to_string()))
+                | Tag::Item => push_inline(&mut inlines, Inline::Raw(p.unexpected(&e)?)),
+                Tag::Emphasis => push_inline(&mut inlines, Inline::Emph(parse_inlines(p)?)),
+                Tag::Strong => push_inline(&mut inlines, Inline::Strong(parse_inlines(p)?)),
+                Tag::Strikethrough => {
+                    push_inline(&mut inlines, Inline::Strikethrough(parse_inlines(p)?));
                 }
+                Tag::Link(ty, destination, title) => push_inline(
+                    &mut inlines,
+                    parse_link(p, *ty, destination, title, Inline::Link)?,
+                ),
+                Tag::Image(ty, destination, title) => push_inline(
+                    &mut inlines,
+                    parse_link(p, *ty, destination, title, Inline::Image)?,
+                ),
             },
             Event::End(_) => break,
-            Event::Text(s) => inlines.push(Inline::Plain(s.to_string())),
-            Event::Code(s) => inlines.push(Inline::Code(s.to_string())),
-            Event::Html(s) => inlines.push(Inline::Html(s.to_string())),
-            Event::SoftBreak => inlines.push(Inline::SoftBreak),
-            Event::HardBreak => inlines.push(Inline::HardBreak),
-            Event::Rule => continue,
-            Event::FootnoteReference(_) | Event::TaskListMarker(_) => {
-                return Err(ParseError::UnexpectedElement {
-                    event: format!("{:?}", e),
-                })
+            Event::Text(s) => push_inline(&mut inlines, Inline::Plain(s.to_string())),
+            Event::Code(s) => push_inline(&mut inlines, Inline::Code(s.to_string())),
+            Event::Html(s) => push_inline(&mut inlines, Inline::Html(s.to_string())),
+            Event::SoftBreak => push_inline(&mut inlines, Inline::SoftBreak),
+            Event::HardBreak => push_inline(&mut inlines, Inline::HardBreak),
+            Event::FootnoteReference(s) => {
+                push_inline(&mut inlines, Inline::FootnoteReference(s.to_string()));
             }
+            Event::Rule => {}
+            Event::TaskListMarker(_) => push_inline(&mut inlines, Inline::Raw(p.unexpected(&e)?)),
         }
     }
 
     Ok(inlines)
 }
 
-fn parse_text(p: &mut Peekable<Parser>) -> Result<String, ParseError> {
-    let e = match p.next() {
-        None => return Ok("".to_string()),
-        Some(e) => e,
-    };
+/// Parse the text of a link or image after its start event.
+fn parse_link(
+    p: &mut Events,
+    ty: pulldown_cmark::LinkType,
+    destination: &str,
+    title: &str,
+    inline: fn(Link) -> Inline,
+) -> Result<Inline, ParseError> {
+    let range = p.range.clone();
+    let link = parse_text(p).map(|text| {
+        inline(Link {
+            link_type: convert_link_type(ty, p.source_of(range.clone())),
+            text,
+            destination: destination.to_string(),
+            title: title.to_string(),
+        })
+    });
+    p.recover(range, link, Inline::Raw)
+}
 
-    trace!("parse_text: {:?}", e);
+/// Convert the type of the link written as `source`.
+fn convert_link_type(ty: pulldown_cmark::LinkType, source: &str) -> LinkType {
+    use pulldown_cmark::LinkType as Type;
 
-    let ret = match e {
-        Event::Text(s) => s.to_string(),
-        _ => {
-            return Err(ParseError::NoText {
-                event: format!("{:?}", e),
-            })
+    match ty {
+        Type::Inline => LinkType::Inline,
+        Type::Reference | Type::ReferenceUnknown => {
+            // the label isn't part of the event, so read it from the end of the link
+            let label = source
+                .strip_suffix(']')
+                .and_then(|s| s.rsplit_once('['))
+                .map_or("", |(_, label)| label);
+            LinkType::Reference(label.to_string())
         }
-    };
+        Type::Collapsed | Type::CollapsedUnknown => LinkType::Collapsed,
+        Type::Shortcut | Type::ShortcutUnknown => LinkType::Shortcut,
+        Type::Autolink => LinkType::Autolink,
+        Type::Email => LinkType::Email,
+    }
+}
+
+fn parse_text(p: &mut Events) -> Result<String, ParseError> {
+    let mut text = String::new();
 
-    match p.next() {
-        None | Some(Event::End(_)) => Ok(ret),
-        Some(e) => Err(ParseError::ExpectedEnd {
-            event: format!("{:?}", e),
-        }),
+    // text is split into several events at escapes and entities
+    while let Some(e) = p.next() {
+        trace!("parse_text: {:?}", e);
+
+        match e {
+            Event::Text(s) => text.push_str(&s),
+            Event::End(_) => break,
+            _ => {
+                return Err(ParseError::NoText {
+                    event: format!("{e:?}"),
+                    location: p.location(),
+                })
+            }
+        }
     }
+
+    Ok(text)
 }
 
 impl Parse for Markdown {
     type ParseError = ParseError;
 
     fn parse(s: &str) -> Result<Nodo, Self::ParseError> {
+        Self::parse_with(s, ParseOptions::default())
+    }
+}
+
+impl Markdown {
+    /// Parse a nodo with the given options.
+    ///
+    /// # Errors
+    ///
+    /// Fails on invalid front matter or, unless lenient, on markdown that can't be represented.
+    pub fn parse_with(s: &str, options: ParseOptions) -> Result<Nodo, ParseError> {
+        let (metadata, content) = Metadata::split(s)?;
         let mut opts = Options::empty();
         opts.insert(Options::ENABLE_TASKLISTS);
         opts.insert(Options::ENABLE_STRIKETHROUGH);
-        let blocks = parse_blocks(&mut (Parser::new_ext(s, opts)).peekable())?;
-        Ok(Nodo { blocks })
+        opts.insert(Options::ENABLE_TABLES);
+        opts.insert(Options::ENABLE_FOOTNOTES);
+        let blocks = parse_blocks(&mut Events::new(s, content, opts, options.lenient))?;
+        Ok(Nodo { metadata, blocks })
     }
 }
 
-fn render_blocks<W: std::io::Write>(
-    bs: &[Block],
-    prefix: &str,
-    w: &mut W,
-    compact: bool,
-) -> Result<(), std::io::Error> {
-    for (i, b) in bs.iter().enumerate() {
-        trace!("render_blocks: {:?}", b);
-
-        match b {
-            Block::Rule => write!(w, "{}---", prefix)?,
-            Block::Paragraph(inlines) => render_inlines(inlines, prefix, w)?,
-            Block::Heading(level, inlines) => {
-                write!(w, "{}{} ", prefix, "#".repeat(*level as usize))?;
-                render_inlines(inlines, prefix, w)?
-            }
-            Block::Code(lang, content) => write!(w, "{}```{}\n{}```", prefix, lang, content)?,
-            Block::Quote(blocks) => {
-                let prefix = format!("{}> ", prefix);
-                write!(w, "{}", prefix)?;
-                render_blocks(blocks, &prefix, w, compact)?
-            }
-            Block::List(ty, items) => render_list_items(*ty, items, prefix, w)?,
-        }
-
-        if i != bs.len() - 1 {
-            if compact {
-                if let Some(Block::List(_, _)) = bs.get(i + 1) {
-                    write!(w, "\n{}", prefix)?
-                } else {
-                    write!(w, "\n\n{}", prefix)?
-                }
-            } else {
-                write!(w, "\n\n{}", prefix)?
-            }
+/// Writes through to the underlying writer, holding on to the first error so that rendering can
+/// be driven by the infallible [`Visit`] methods.
+struct Output<'w, W> {
+    w: &'w mut W,
+    result: io::Result<()>,
+}
+
+impl<W: io::Write> Output<'_, W> {
+    fn write_fmt(&mut self, args: fmt::Arguments) {
+        if self.result.is_ok() {
+            self.result = self.w.write_fmt(args);
         }
     }
-    Ok(())
 }
 
-fn render_list_items<W: std::io::Write>(
-    list_type: ListType,
-    is: &[ListItem],
-    prefix: &str,
-    w: &mut W,
-) -> Result<(), std::io::Error> {
-    for (i, item) in is.iter().enumerate() {
-        trace!("render_list_items: {:?}", item);
+/// The kind of block that text is written in, which decides what needs escaping.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum TextContext {
+    Paragraph,
+    Heading,
+    TableCell,
+    LinkText,
+}
 
-        match list_type {
-            ListType::Numbered => write!(w, "{}{}. ", if i == 0 { "" } else { prefix }, i + 1)?,
-            ListType::Plain => write!(w, "{}- ", if i == 0 { "" } else { prefix })?,
-        }
+struct Renderer<'w, W> {
+    out: Output<'w, W>,
+    prefix: String,
+    compact: bool,
+    text: TextContext,
+    /// Whether the next inline starts a line, where it could be mistaken for a block marker.
+    line_start: bool,
+    /// Whether the next inline is a link or footnote reference, which a trailing `!` would make
+    /// an image.
+    before_link: bool,
+    /// The width of the marker of the list item being rendered.
+    marker_width: usize,
+}
 
-        if let Some(b) = item.task {
-            if b {
-                write!(w, "[x] ")?
-            } else {
-                write!(w, "[ ] ")?
-            }
+impl<'w, W: io::Write> Renderer<'w, W> {
+    const fn new(w: &'w mut W) -> Self {
+        Self {
+            out: Output { w, result: Ok(()) },
+            prefix: String::new(),
+            compact: false,
+            text: TextContext::Paragraph,
+            line_start: false,
+            before_link: false,
+            marker_width: 0,
         }
+    }
 
-        render_blocks(&item.blocks, &format!("{}{}", prefix, INDENT), w, true)?;
-
-        if i != is.len() - 1 {
-            writeln!(w)?
-        }
+    /// Run `f` with `suffix` added to the prefix of each new line.
+    fn indented<F: FnOnce(&mut Self)>(&mut self, suffix: &str, compact: bool, f: F) {
+        let prefix_len = self.prefix.len();
+        let old_compact = std::mem::replace(&mut self.compact, compact);
+        self.prefix.push_str(suffix);
+        f(self);
+        self.prefix.truncate(prefix_len);
+        self.compact = old_compact;
     }
 
-    Ok(())
-}
+    fn render_list_items(&mut self, list_type: ListType, is: &[ListItem]) {
+        for (i, item) in is.iter().enumerate() {
+            trace!("render_list_items: {:?}", item);
 
-fn render_inlines<W: std::io::Write>(
-    is: &[Inline],
-    prefix: &str,
-    w: &mut W,
-) -> Result<(), std::io::Error> {
-    for item in is.iter() {
-        trace!("render_inlines: {:?}", item);
+            let prefix = if i == 0 { "" } else { self.prefix.as_str() };
+            let marker = match list_type {
+                ListType::Numbered(start, delimiter) => {
+                    format!("{}{} ", start + i as u64, delimiter)
+                }
+                ListType::Plain(bullet) => format!("{bullet} "),
+            };
+            write!(self.out, "{prefix}{marker}");
+            self.marker_width = marker.len();
 
-        match item {
-            Inline::Plain(s) | Inline::Html(s) => write!(w, "{}", s)?,
-            Inline::Emph(i) => {
-                write!(w, "*")?;
-                render_inlines(i, "", w)?;
-                write!(w, "*")?
-            }
-            Inline::Strong(i) => {
-                write!(w, "**")?;
-                render_inlines(i, "", w)?;
-                write!(w, "**")?
-            }
-            Inline::Code(s) => write!(w, "`{}`", s)?,
-            Inline::Strikethrough(i) => {
-                write!(w, "~~")?;
-                render_inlines(i, "", w)?;
-                write!(w, "~~")?
-            }
-            Inline::Link(n, l) => write!(w, "[{}]({})", n, l)?,
-            Inline::Image(n, l) => write!(w, "![{}]({})", n, l)?,
-            Inline::SoftBreak => {
-                writeln!(w)?;
-                write!(w, "{}", prefix)?
-            }
-            Inline::HardBreak => {
-                write!(w, "\n\n")?;
-                write!(w, "{}", prefix)?
+            self.visit_list_item(item);
+
+            if i != is.len() - 1 {
+                writeln!(self.out);
             }
         }
     }
-    Ok(())
-}
-
-impl Render for Markdown {
-    type RenderError = RenderError;
 
-    fn render<W: std::io::Write>(n: &Nodo, w: &mut W) -> Result<(), Self::RenderError> {
-        render_blocks(&n.blocks, "", w, false)?;
-        if !n.blocks.is_empty() {
-            writeln!(w)?;
+    fn render_link(&mut self, link: &Link) {
+        // the text of collapsed and shortcut links is also the label, so has to be kept as is
+        let text = escape(&link.text, false, TextContext::LinkText);
+        match &link.link_type {
+            LinkType::Inline => {
+                write!(self.out, "[{text}](");
+                self.render_destination(&link.destination, &link.title);
+                write!(self.out, ")");
+            }
+            LinkType::Reference(label) => write!(self.out, "[{text}][{label}]"),
+            LinkType::Collapsed => write!(self.out, "[{}][]", link.text),
+            LinkType::Shortcut => write!(self.out, "[{}]", link.text),
+            LinkType::Autolink | LinkType::Email => write!(self.out, "<{}>", link.destination),
         }
-        Ok(())
+    }
+
+    /// Write a link destination followed by its title, if it has one.
+    fn render_destination(&mut self, destination: &str, title: &str) {
+        let mut depth = 0_usize;
+        let balanced = destination.chars().all(|c| {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => return false,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            true
+        }) && depth == 0;
+
+        if destination.is_empty() || destination.contains(char::is_whitespace) || !balanced {
+            let destination = destination.replace('<', "\\<").replace('>', "\\>");
+            write!(self.out, "<{destination}>");
+        } else {
+            write!(self.out, "{destination}");
+        }
+        if !title.is_empty() {
+            let title = title
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', &["\n", &self.prefix].concat());
+            write!(self.out, " \"{title}\"");
+        }
+    }
+
+    fn render_code_block(&mut self, lang: &str, content: &str) {
+        // an info string can't contain backticks when fenced with them
+        let fence_char = if lang.contains('`') { '~' } else { '`' };
+        let fence = fence_char
+            .to_string()
+            .repeat((longest_run(content, fence_char) + 1).max(3));
+
+        write!(self.out, "{fence}{lang}");
+        for line in content.lines() {
+            if line.is_empty() {
+                write!(self.out, "\n{}", self.prefix.trim_end());
+            } else {
+                write!(self.out, "\n{}{}", self.prefix, line);
+            }
+        }
+        write!(self.out, "\n{}{}", self.prefix, fence);
+    }
+
+    fn render_code_span(&mut self, content: &str) {
+        let fence = "`".repeat(longest_run(content, '`') + 1);
+        // one space is stripped from each side when both are there, and backticks at either end
+        // would join the fence
+        let padded = content.starts_with('`')
+            || content.ends_with('`')
+            || (content.starts_with(' ')
+                && content.ends_with(' ')
+                && !content.trim_start_matches(' ').is_empty());
+        let padding = if padded { " " } else { "" };
+        write!(self.out, "{fence}{padding}{content}{padding}{fence}");
+    }
+
+    fn render_table(
+        &mut self,
+        alignments: &[Alignment],
+        header: &[Vec<Inline>],
+        rows: &[TableRow],
+    ) {
+        let render_row = |row: &[Vec<Inline>]| {
+            row.iter()
+                .map(|cell| {
+                    // writing to a vec can't fail
+                    let mut buf = Vec::new();
+                    let mut renderer = Renderer::new(&mut buf);
+                    renderer.text = TextContext::TableCell;
+                    renderer.visit_inlines(cell);
+                    String::from_utf8_lossy(&buf).into_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let header = render_row(header);
+        let body = rows.iter().map(|r| render_row(r)).collect::<Vec<_>>();
+
+        // rows can have more cells than the delimiter row has columns, such cells get a column
+        // without alignment so they aren't lost
+        let columns = std::iter::once(&header)
+            .chain(body.iter())
+            .map(Vec::len)
+            .fold(alignments.len(), usize::max);
+        let alignment = |i: usize| alignments.get(i).copied().unwrap_or(Alignment::None);
+
+        // each column is at least as wide as the shortest valid delimiter, `:-:`
+        let mut widths = vec![3; columns];
+        for row in std::iter::once(&header).chain(body.iter()) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let write_row = |cells: &[String], out: &mut Output<W>| {
+            write!(out, "|");
+            for (i, width) in widths.iter().enumerate() {
+                let cell = cells.get(i).map_or("", String::as_str);
+                let padding = width - cell.chars().count();
+                let (left, right) = match alignment(i) {
+                    Alignment::None | Alignment::Left => (0, padding),
+                    Alignment::Center => (padding / 2, padding - padding / 2),
+                    Alignment::Right => (padding, 0),
+                };
+                write!(out, " {}{}{} |", " ".repeat(left), cell, " ".repeat(right));
+            }
+        };
+
+        write_row(&header, &mut self.out);
+
+        write!(self.out, "\n{}|", self.prefix);
+        for (i, width) in widths.iter().enumerate() {
+            let delimiter = match alignment(i) {
+                Alignment::None => "-".repeat(*width),
+                Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+                Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+            };
+            write!(self.out, " {delimiter} |");
+        }
+
+        for row in &body {
+            write!(self.out, "\n{}", self.prefix);
+            write_row(row, &mut self.out);
+        }
+    }
+}
+
+/// The length of the longest run of `c` in `s`.
+fn longest_run(s: &str, c: char) -> usize {
+    s.split(|x| x != c).map(str::len).max().unwrap_or(0)
+}
+
+/// Escape text so that it reads back as the same text rather than as markup.
+///
+/// Only characters that could start markup where they are get escaped, to keep the text
+/// readable.
+fn escape(s: &str, line_start: bool, context: TextContext) -> String {
+    let chars = s.chars().collect::<Vec<_>>();
+    let marker = match (line_start, context) {
+        (false, _) => None,
+        // the text of a heading can only be mistaken for the task marker of a heading
+        (true, TextContext::Heading) => block_marker(&chars).filter(|_| chars[0] == '['),
+        (true, _) => block_marker(&chars),
+    };
+
+    let mut escaped = String::with_capacity(s.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1).copied();
+        let rest = &chars[i + 1..];
+        let needs_escape = marker == Some(i)
+            || match c {
+                // pipes can start a table anywhere
+                '\\' | '*' | '`' | '~' | '|' => true,
+                // underscores within a word can't be emphasis
+                '_' => {
+                    !(prev.is_some_and(char::is_alphanumeric)
+                        && next.is_some_and(char::is_alphanumeric))
+                }
+                '[' | ']' if context == TextContext::LinkText => true,
+                '[' => opens_link(rest),
+                '<' => {
+                    next.is_some_and(|n| n.is_ascii_alphabetic() || matches!(n, '/' | '!' | '?'))
+                }
+                '&' => starts_entity(rest),
+                // a closing sequence of a heading
+                '#' => {
+                    context == TextContext::Heading
+                        && prev.is_none_or(char::is_whitespace)
+                        && rest.iter().all(|&c| c == '#')
+                }
+                _ => false,
+            };
+        if needs_escape {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The index of the character to escape if a line starting with `chars` would start a block.
+fn block_marker(chars: &[char]) -> Option<usize> {
+    let followed_by_space = |n: usize| chars.get(n).is_none_or(|c| c.is_whitespace());
+    let run = |c: char| chars.iter().take_while(|&&x| x == c).count();
+
+    match *chars.first()? {
+        '>' => Some(0),
+        '#' => (run('#') <= 6 && followed_by_space(run('#'))).then_some(0),
+        '-' | '+' => (followed_by_space(1) || run(chars[0]) == chars.len()).then_some(0),
+        // a setext heading underline
+        '=' => (run('=') == chars.len()).then_some(0),
+        '0'..='9' => {
+            let digits = chars.iter().take_while(|c| c.is_ascii_digit()).count();
+            (digits <= 9
+                && matches!(chars.get(digits), Some('.' | ')'))
+                && followed_by_space(digits + 1))
+            .then_some(digits)
+        }
+        // a task list marker
+        '[' => matches!(chars.get(1..3), Some([' ' | 'x' | 'X', ']'])).then_some(0),
+        _ => None,
+    }
+}
+
+/// Whether a `[` followed by `rest` could open a link, footnote reference or definition.
+///
+/// The closing `]` could be in a later inline when there isn't one in `rest`.
+fn opens_link(rest: &[char]) -> bool {
+    rest.iter().position(|&c| c == ']').is_none_or(|end| {
+        rest[..end].iter().any(|c| !c.is_whitespace())
+            || matches!(rest.get(end + 1), Some('(' | '[' | ':'))
+    })
+}
+
+/// Whether a `&` followed by `rest` would be read as an entity or character reference.
+fn starts_entity(rest: &[char]) -> bool {
+    let name = rest
+        .iter()
+        .take_while(|c| c.is_ascii_alphanumeric() || **c == '#')
+        .count();
+    name > 0 && rest.get(name) == Some(&';')
+}
+
+/// Whether `next` follows `b` on the next line rather than after a blank line.
+const fn is_tight(b: &Block, next: &Block, compact: bool) -> bool {
+    match (&b.kind, &next.kind) {
+        (BlockKind::ReferenceDefinition(_, _, _), BlockKind::ReferenceDefinition(_, _, _)) => true,
+        (_, BlockKind::List(_, _)) => compact,
+        _ => false,
+    }
+}
+
+impl<'a, W: io::Write> Visit<'a> for Renderer<'_, W> {
+    fn visit_blocks(&mut self, bs: &'a [Block]) {
+        for (i, b) in bs.iter().enumerate() {
+            self.visit_block(b);
+
+            if let Some(next) = bs.get(i + 1) {
+                if is_tight(b, next, self.compact) {
+                    write!(self.out, "\n{}", self.prefix);
+                } else {
+                    // keep the blank line within any quote
+                    write!(self.out, "\n{}\n{}", self.prefix.trim_end(), self.prefix);
+                }
+            }
+        }
+    }
+
+    fn visit_block(&mut self, b: &'a Block) {
+        trace!("render_block: {:?}", b);
+
+        match &b.kind {
+            BlockKind::Rule => write!(self.out, "---"),
+            BlockKind::Raw(s) => write!(self.out, "{s}"),
+            BlockKind::Html(html) => {
+                for (i, line) in html.lines().enumerate() {
+                    match i {
+                        0 => write!(self.out, "{line}"),
+                        _ if line.is_empty() => write!(self.out, "\n{}", self.prefix.trim_end()),
+                        _ => write!(self.out, "\n{}{}", self.prefix, line),
+                    }
+                }
+            }
+            BlockKind::Paragraph(inlines) => {
+                self.text = TextContext::Paragraph;
+                self.line_start = true;
+                self.visit_inlines(inlines);
+            }
+            BlockKind::Heading(level, task, inlines) => {
+                write!(self.out, "{} ", "#".repeat(*level as usize));
+                // markdown has no heading tasks, so they are written like task list items
+                if let Some(state) = task {
+                    write!(self.out, "[{}] ", state.marker());
+                }
+                self.text = TextContext::Heading;
+                self.line_start = true;
+                self.visit_inlines(inlines);
+            }
+            BlockKind::Code(lang, content) => self.render_code_block(lang, content),
+            BlockKind::Quote(blocks) => {
+                write!(self.out, "> ");
+                self.indented("> ", self.compact, |r| r.visit_blocks(blocks));
+            }
+            BlockKind::List(ty, items) => self.render_list_items(*ty, items),
+            BlockKind::Table(alignments, header, rows) => {
+                self.render_table(alignments, header, rows);
+            }
+            BlockKind::FootnoteDefinition(label, blocks) => {
+                write!(self.out, "[^{label}]: ");
+                self.indented(INDENT, self.compact, |r| r.visit_blocks(blocks));
+            }
+            BlockKind::ReferenceDefinition(label, destination, title) => {
+                write!(self.out, "[{label}]: ");
+                self.render_destination(destination, title);
+            }
+        }
+    }
+
+    fn visit_list_item(&mut self, item: &'a ListItem) {
+        if let Some(state) = item.task {
+            write!(self.out, "[{}] ", state.marker());
+        }
+
+        // the content of an item has to be indented at least as far as the marker is wide
+        let indent = self.marker_width.max(INDENT.len());
+        // the lines of a code block are indented relative to its opening fence, so one that
+        // starts the item has to start at the same indent as them
+        let code = item
+            .blocks
+            .first()
+            .is_some_and(|b| matches!(b.kind, BlockKind::Code(_, _)));
+        if item.task.is_none() && code {
+            write!(self.out, "{}", " ".repeat(indent - self.marker_width));
+        }
+        self.indented(&" ".repeat(indent), true, |r| walk_list_item(r, item));
+    }
+
+    fn visit_inlines(&mut self, is: &'a [Inline]) {
+        for (i, inline) in is.iter().enumerate() {
+            self.before_link = matches!(
+                is.get(i + 1),
+                Some(Inline::Link(_) | Inline::FootnoteReference(_))
+            );
+            self.visit_inline(inline);
+        }
+    }
+
+    fn visit_inline(&mut self, i: &'a Inline) {
+        trace!("render_inline: {:?}", i);
+
+        let line_start = std::mem::replace(&mut self.line_start, false);
+        match i {
+            Inline::Plain(s) => {
+                let mut s = escape(s, line_start, self.text);
+                if self.before_link && s.ends_with('!') {
+                    s.insert(s.len() - 1, '\\');
+                }
+                write!(self.out, "{s}");
+            }
+            Inline::Html(s) | Inline::Raw(s) => write!(self.out, "{s}"),
+            Inline::Emph(_) => {
+                write!(self.out, "*");
+                walk_inline(self, i);
+                write!(self.out, "*");
+            }
+            Inline::Strong(_) => {
+                write!(self.out, "**");
+                walk_inline(self, i);
+                write!(self.out, "**");
+            }
+            Inline::Code(s) => self.render_code_span(s),
+            Inline::Strikethrough(_) => {
+                write!(self.out, "~~");
+                walk_inline(self, i);
+                write!(self.out, "~~");
+            }
+            Inline::Link(link) => self.render_link(link),
+            Inline::Image(link) => {
+                write!(self.out, "!");
+                self.render_link(link);
+            }
+            Inline::FootnoteReference(label) => write!(self.out, "[^{label}]"),
+            Inline::SoftBreak => {
+                write!(self.out, "\n{}", self.prefix);
+                self.line_start = true;
+            }
+            Inline::HardBreak => {
+                write!(self.out, "\\\n{}", self.prefix);
+                self.line_start = true;
+            }
+        }
+    }
+}
+
+impl Render for Markdown {
+    type RenderError = RenderError;
+
+    fn render<W: std::io::Write>(n: &Nodo, w: &mut W) -> Result<(), Self::RenderError> {
+        if let Some(metadata) = n.metadata.as_ref() {
+            write!(w, "{}", metadata.render()?)?;
+            if !n.blocks.is_empty() {
+                writeln!(w)?;
+            }
+        }
+
+        // footnote definitions are collected at the end of the document, in the order they were
+        // defined, so that their position is stable across edits
+        let (definitions, blocks): (Vec<_>, Vec<_>) = n
+            .blocks
+            .iter()
+            .partition(|b| matches!(b.kind, BlockKind::FootnoteDefinition(_, _)));
+
+        let mut renderer = Renderer::new(w);
+        let mut previous = None;
+        for b in blocks.iter().chain(definitions.iter()) {
+            match previous {
+                Some(previous) if is_tight(previous, b, false) => writeln!(renderer.out),
+                Some(_) => write!(renderer.out, "\n\n"),
+                None => {}
+            }
+            renderer.visit_block(b);
+            previous = Some(b);
+        }
+        if !n.blocks.is_empty() {
+            writeln!(renderer.out);
+        }
+        Ok(renderer.out.result?)
     }
 }
 
@@ -500,7 +1500,7 @@ mod tests {
 
     macro_rules! assert_eq {
         ($left:expr, $right:expr) => {
-            pretty_assertions::assert_eq!(PrettyString($left), PrettyString($right));
+            pretty_assertions::assert_eq!(PrettyString($left), PrettyString($right))
         };
     }
 
@@ -550,4 +1550,716 @@ more text
 
         assert_eq!(md, &String::from_utf8(out).unwrap())
     }
+
+    #[test]
+    fn parse_and_write_tables() {
+        let md = "| name | *kind* | count |
+| :--- | :----: | ----: |
+| a    |  `x`   |     1 |
+| bcde |   yy   |    10 |
+
+> | a   | b   |
+> | --- | --- |
+> | c   |     |
+";
+        let nodo = Markdown::parse(md).unwrap();
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+
+        assert_eq!(md, &String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn write_escapes_are_read_back() {
+        for (md, expected) in &[
+            (
+                "| a |\n|---|\n| [x\\|y](z) |\n",
+                "| a         |\n| --------- |\n| [x\\|y](z) |\n",
+            ),
+            // the parser takes the `!` before a footnote reference as part of it
+            ("!![^1]\n", "\\![^1]\n"),
+        ] {
+            let nodo = Markdown::parse(md).unwrap();
+
+            let mut out = Vec::new();
+            Markdown::render(&nodo, &mut out).unwrap();
+            let out = String::from_utf8(out).unwrap();
+
+            assert_eq!(expected, &out);
+            std::assert_eq!(nodo, Markdown::parse(&out).unwrap());
+        }
+    }
+
+    #[test]
+    fn parse_and_write_footnotes() {
+        let md = "a claim[^1] and another[^note]
+
+[^1]: the first source
+
+[^note]: a *longer* note
+";
+        let nodo = Markdown::parse(md).unwrap();
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+
+        assert_eq!(md, &String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn render_collects_footnote_definitions_at_end() {
+        let md = "[^a]: defined early
+
+text[^a]
+
+# heading
+";
+        let nodo = Markdown::parse(md).unwrap();
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+
+        assert_eq!(
+            "text[^a]
+
+# heading
+
+[^a]: defined early
+",
+            &String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_and_write_front_matter() {
+        let md = "---
+title: A note
+tags:
+  - work
+---
+
+# heading
+";
+        let nodo = Markdown::parse(md).unwrap();
+        assert_eq!(nodo.metadata().unwrap().title().unwrap(), "A note");
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+
+        assert_eq!(md, &String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn parse_and_write_rules_around_invalid_front_matter() {
+        let md = "---
+
+Note: see [x](y): here
+
+---
+";
+        let nodo = Markdown::parse(md).unwrap();
+        std::assert_eq!(nodo.metadata(), None);
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+
+        assert_eq!(md, &String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn parse_records_spans() {
+        let md = "---
+title: spans
+---
+
+# heading
+
+- one
+- [ ] twö
+";
+        let nodo = Markdown::parse(md).unwrap();
+        let heading = &nodo.blocks()[0];
+        let list = &nodo.blocks()[1];
+
+        std::assert_eq!(
+            heading.span(),
+            Span {
+                start: Position {
+                    offset: 22,
+                    line: 5,
+                    column: 1
+                },
+                end: Position {
+                    offset: 32,
+                    line: 6,
+                    column: 1
+                },
+            }
+        );
+        std::assert_eq!(&md[list.span().range()], "- one\n- [ ] twö\n");
+
+        let BlockKind::List(_, items) = list.kind() else {
+            unreachable!()
+        };
+        std::assert_eq!(items[1].span().start.line, 8);
+        std::assert_eq!(items[1].span().end.column, 1);
+        std::assert_eq!(&md[items[1].span().range()], "- [ ] twö\n");
+    }
+
+    #[test]
+    fn parse_error_location() {
+        let md = "# heading
+
+- [*emphasised* link](dest)
+";
+        let err = Markdown::parse(md).unwrap_err();
+
+        assert_eq!(
+            &err.to_string(),
+            "received non-text event while parsing plaintext: Start(Emphasis)
+ --> 3:4
+  |
+3 | - [*emphasised* link](dest)
+  |    ^^^^^^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn parse_lenient_keeps_raw_source() {
+        let md = "---
+title: raw
+---
+
+# heading
+
+<div align=\"center\">
+  <b>html</b>
+</div>
+
+- `code` starting an item
+- [*emphasised* link](dest)
+
+> a [**strong** link](dest) in a quote
+";
+        let lenient = ParseOptions { lenient: true };
+        let nodo = Markdown::parse_with(md, lenient).unwrap();
+
+        std::assert_eq!(
+            nodo.blocks()[1].kind(),
+            &BlockKind::Html("<div align=\"center\">\n  <b>html</b>\n</div>".to_string())
+        );
+        assert!(nodo
+            .iter_inlines()
+            .any(|i| i == &Inline::Raw("[*emphasised* link](dest)".to_string())));
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+        assert_eq!(md, &String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn render_aligns_table_columns() {
+        let md = "|a|b|
+|-|:-:|
+|long cell|c|
+";
+        let nodo = Markdown::parse(md).unwrap();
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+
+        assert_eq!(
+            "| a         |  b  |
+| --------- | :-: |
+| long cell |  c  |
+",
+            &String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn render_keeps_cells_beyond_delimiter_row() {
+        let md = "|\n\n- [ ] x\n";
+        let nodo = Markdown::parse(md).unwrap();
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+
+        assert_eq!(
+            "|         |
+| ------- |
+| - [ ] x |
+",
+            &String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_and_write_list_markers() {
+        let md = "5. five
+6. six
+
+* star
+    3) nested
+
++ plus
+
+-   ```
+    code first
+    ```
+";
+        let nodo = Markdown::parse(md).unwrap();
+        std::assert_eq!(
+            nodo.blocks()[0].kind(),
+            &BlockKind::List(
+                ListType::Numbered(5, '.'),
+                vec![
+                    ListItem::new(
+                        None,
+                        vec![BlockKind::Paragraph(vec![Inline::Plain("five".to_string())]).into()]
+                    ),
+                    ListItem::new(
+                        None,
+                        vec![BlockKind::Paragraph(vec![Inline::Plain("six".to_string())]).into()]
+                    ),
+                ]
+            )
+        );
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+        assert_eq!(md, &String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn parse_and_write_links() {
+        let md = "An [inline](https://a.example \"A title\") link, a [reference][ref], a [collapsed][] and
+a [shortcut] link, with an ![image](cat.png) and <https://auto.example>.
+
+- ![alt][ref] in <me@example.com>
+
+[ref]: https://ref.example 'Ref title'
+[collapsed]: <a b>
+[shortcut]: /short
+";
+        let nodo = Markdown::parse(md).unwrap();
+
+        let links = nodo
+            .iter_inlines()
+            .filter_map(|i| match i {
+                Inline::Link(link) | Inline::Image(link) => Some(link.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        std::assert_eq!(
+            links[0],
+            Link {
+                link_type: LinkType::Inline,
+                text: "inline".to_string(),
+                destination: "https://a.example".to_string(),
+                title: "A title".to_string(),
+            }
+        );
+        std::assert_eq!(
+            links
+                .iter()
+                .map(|l| l.link_type.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                LinkType::Inline,
+                LinkType::Reference("ref".to_string()),
+                LinkType::Collapsed,
+                LinkType::Shortcut,
+                LinkType::Inline,
+                LinkType::Autolink,
+                LinkType::Reference("ref".to_string()),
+                LinkType::Email,
+            ]
+        );
+        std::assert_eq!(
+            nodo.blocks()[2].kind(),
+            &BlockKind::ReferenceDefinition(
+                "ref".to_string(),
+                "https://ref.example".to_string(),
+                "Ref title".to_string()
+            )
+        );
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+        assert_eq!(
+            &md.replace("'Ref title'", "\"Ref title\""),
+            &String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_and_write_multiline_reference_definitions() {
+        let md = "[foo]:
+  /url
+  \"title\"
+[bar]: /url
+  \"long title\"
+
+> [baz]: /quoted
+> 'over
+> lines'
+";
+        let nodo = Markdown::parse(md).unwrap();
+        std::assert_eq!(
+            nodo.blocks()[..2]
+                .iter()
+                .map(Block::kind)
+                .cloned()
+                .collect::<Vec<_>>(),
+            vec![
+                BlockKind::ReferenceDefinition(
+                    "foo".to_string(),
+                    "/url".to_string(),
+                    "title".to_string()
+                ),
+                BlockKind::ReferenceDefinition(
+                    "bar".to_string(),
+                    "/url".to_string(),
+                    "long title".to_string()
+                ),
+            ]
+        );
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert_eq!(
+            "[foo]: /url \"title\"
+[bar]: /url \"long title\"
+
+> [baz]: /quoted \"over
+> lines\"
+",
+            &written
+        );
+        std::assert_eq!(Markdown::parse(&written).unwrap().blocks(), nodo.blocks());
+    }
+
+    #[test]
+    fn parse_lenient_keeps_unparsed_reference_definitions() {
+        let md = "[split
+label]: /url
+
+[after]: /after
+";
+        assert!(matches!(
+            Markdown::parse(md),
+            Err(ParseError::InvalidReferenceDefinition { .. })
+        ));
+
+        let nodo = Markdown::parse_with(md, ParseOptions { lenient: true }).unwrap();
+        std::assert_eq!(
+            nodo.blocks()[0].kind(),
+            &BlockKind::Raw("[split\nlabel]: /url".to_string())
+        );
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+        assert_eq!(md, &String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn parse_and_write_task_states() {
+        let md = "- [ ] open
+- [x] done
+- [-] in progress
+- [~] cancelled
+- [>] deferred
+- \\[-] not a task
+";
+        let nodo = Markdown::parse(md).unwrap();
+        let tasks = nodo.list_items().map(ListItem::task).collect::<Vec<_>>();
+        std::assert_eq!(
+            tasks,
+            vec![
+                Some(TaskState::Open),
+                Some(TaskState::Done),
+                Some(TaskState::InProgress),
+                Some(TaskState::Cancelled),
+                Some(TaskState::Deferred),
+                None,
+            ]
+        );
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+        assert_eq!(md, &String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn parse_and_write_heading_tasks() {
+        let md = "# [ ] open
+
+## [~] cancelled
+
+## \\[ ] not a task
+";
+        let nodo = Markdown::parse(md).unwrap();
+        let headings = nodo
+            .blocks()
+            .iter()
+            .map(|b| match b.kind() {
+                BlockKind::Heading(_, task, inlines) => (*task, inlines.clone()),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        std::assert_eq!(
+            headings,
+            vec![
+                (
+                    Some(TaskState::Open),
+                    vec![Inline::Plain("open".to_string())]
+                ),
+                (
+                    Some(TaskState::Cancelled),
+                    vec![Inline::Plain("cancelled".to_string())]
+                ),
+                (None, vec![Inline::Plain("[ ] not a task".to_string())]),
+            ]
+        );
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+        assert_eq!(md, &String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn parse_and_write_html() {
+        let md = "<!-- status: draft -->
+
+<details>
+<summary>More</summary>
+
+hidden *text*
+
+</details>
+
+> <div>
+> quoted
+> </div>
+
+- <b>inline</b> html
+- item
+
+    <!--
+    multi line
+
+    comment
+    -->
+";
+        let nodo = Markdown::parse(md).unwrap();
+        std::assert_eq!(
+            nodo.blocks()[0].kind(),
+            &BlockKind::Html("<!-- status: draft -->".to_string())
+        );
+        std::assert_eq!(
+            nodo.blocks()[1].kind(),
+            &BlockKind::Html("<details>\n<summary>More</summary>".to_string())
+        );
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+        assert_eq!(md, &String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn render_escapes_markup() {
+        let plain = |s: &str| Inline::Plain(s.to_string());
+        let nodo = Nodo::new(
+            None,
+            vec![
+                BlockKind::Heading(2, None, vec![plain("C# and #")]).into(),
+                BlockKind::Paragraph(vec![
+                    plain("1. *not* a [list] or snake_case _emphasis_ &amp; <b>"),
+                    Inline::SoftBreak,
+                    plain("# nor a heading"),
+                    Inline::HardBreak,
+                    Inline::Code("a `tick`".to_string()),
+                    plain(" wow!"),
+                    Inline::Link(Link {
+                        link_type: LinkType::Inline,
+                        text: "[x]".to_string(),
+                        destination: "a b".to_string(),
+                        title: "say \"hi\"".to_string(),
+                    }),
+                ])
+                .into(),
+                BlockKind::Code(String::new(), "```\n".to_string()).into(),
+            ],
+        );
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+        assert_eq!(
+            r#"## C# and \#
+
+1\. \*not\* a \[list] or snake_case \_emphasis\_ \&amp; \<b>
+\# nor a heading\
+`` a `tick` `` wow\![\[x\]](<a b> "say \"hi\"")
+
+````
+```
+````
+"#,
+            &String::from_utf8(out).unwrap()
+        );
+    }
+
+    mod round_trip {
+        use super::super::*;
+        use proptest::{collection::vec, prelude::*};
+
+        /// Words made up of characters that mean something in markdown.
+        fn text() -> impl Strategy<Value = String> {
+            vec(r"[a-z0-9*_\[\]`#>+=.)!<&;|~\\-]{1,6}", 1..4).prop_map(|ws| ws.join(" "))
+        }
+
+        fn span() -> impl Strategy<Value = Inline> {
+            prop_oneof![
+                4 => text().prop_map(Inline::Plain),
+                1 => r"[a-z` ]{1,6}".prop_map(Inline::Code),
+                1 => text().prop_map(|t| Inline::Emph(vec![Inline::Plain(t)])),
+                1 => text().prop_map(|t| Inline::Strong(vec![Inline::Plain(t)])),
+                1 => (text(), r"[a-z/.:() ]{0,8}", r#"[a-z" ]{0,6}"#).prop_map(
+                    |(text, destination, title)| {
+                        Inline::Link(Link {
+                            link_type: LinkType::Inline,
+                            text,
+                            destination,
+                            title,
+                        })
+                    }
+                ),
+            ]
+        }
+
+        /// Spans separated by spaces or, if `breaks`, line breaks.
+        fn inlines(breaks: bool) -> impl Strategy<Value = Vec<Inline>> {
+            let separator = if breaks {
+                prop_oneof![
+                    Just(Inline::Plain(" ".to_string())),
+                    Just(Inline::SoftBreak),
+                    Just(Inline::HardBreak),
+                ]
+                .boxed()
+            } else {
+                Just(Inline::Plain(" ".to_string())).boxed()
+            };
+
+            vec((separator, span()), 1..5).prop_map(|spans| {
+                let mut inlines = Vec::new();
+                for (i, (separator, span)) in spans.into_iter().enumerate() {
+                    if i != 0 {
+                        push_inline(&mut inlines, separator);
+                    }
+                    push_inline(&mut inlines, span);
+                }
+                inlines
+            })
+        }
+
+        fn list_type() -> impl Strategy<Value = ListType> {
+            prop_oneof![
+                prop::sample::select(vec!['-', '*', '+']).prop_map(ListType::Plain),
+                (0..100_u64, prop::sample::select(vec!['.', ')']))
+                    .prop_map(|(start, delimiter)| ListType::Numbered(start, delimiter)),
+            ]
+        }
+
+        fn task_state() -> impl Strategy<Value = TaskState> {
+            prop::sample::select(vec![
+                TaskState::Open,
+                TaskState::Done,
+                TaskState::InProgress,
+                TaskState::Cancelled,
+                TaskState::Deferred,
+            ])
+        }
+
+        fn code() -> impl Strategy<Value = BlockKind> {
+            ("[a-z]{0,4}", vec("[a-z` ~]{0,6}", 0..3)).prop_map(|(lang, lines)| {
+                BlockKind::Code(lang, lines.iter().map(|l| l.clone() + "\n").collect())
+            })
+        }
+
+        fn block() -> impl Strategy<Value = BlockKind> {
+            let leaf = prop_oneof![
+                4 => inlines(true).prop_map(BlockKind::Paragraph),
+                2 => (1..=6_u32, prop::option::of(task_state()), inlines(false))
+                    .prop_map(|(level, task, is)| BlockKind::Heading(level, task, is)),
+                2 => code(),
+                1 => Just(BlockKind::Rule),
+                1 => "[a-z ]{0,8}".prop_map(|c| BlockKind::Html(format!("<!-- {c} -->"))),
+            ];
+            leaf.prop_recursive(2, 16, 3, |block| {
+                prop_oneof![
+                    vec(block.clone(), 1..3).prop_map(|bs| BlockKind::Quote(separate(bs))),
+                    // items start with a paragraph so that other blocks line up with it
+                    (
+                        list_type(),
+                        vec(
+                            (
+                                prop::option::of(task_state()),
+                                inlines(true),
+                                vec(block, 0..2)
+                            ),
+                            1..3
+                        )
+                    )
+                        .prop_map(|(ty, items)| {
+                            let items = items
+                                .into_iter()
+                                .map(|(task, first, rest)| {
+                                    let mut blocks = vec![BlockKind::Paragraph(first)];
+                                    blocks.extend(rest);
+                                    ListItem::new(task, separate(blocks))
+                                })
+                                .collect();
+                            BlockKind::List(ty, items)
+                        }),
+                    // a code block first starts right after the marker, however wide it is
+                    (list_type(), vec(code(), 1..3)).prop_map(|(ty, codes)| {
+                        let items = codes
+                            .into_iter()
+                            .map(|code| ListItem::new(None, vec![code.into()]))
+                            .collect();
+                        BlockKind::List(ty, items)
+                    }),
+                ]
+            })
+        }
+
+        /// Drop lists that directly follow another list, as they would be read back as one.
+        fn separate(kinds: Vec<BlockKind>) -> Vec<Block> {
+            let mut blocks: Vec<Block> = Vec::new();
+            for kind in kinds {
+                let after_list =
+                    matches!(blocks.last().map(Block::kind), Some(BlockKind::List(_, _)));
+                if !(after_list && matches!(kind, BlockKind::List(_, _))) {
+                    blocks.push(kind.into());
+                }
+            }
+            blocks
+        }
+
+        proptest! {
+            #[test]
+            fn parse_render(blocks in vec(block(), 1..4).prop_map(separate)) {
+                let nodo = Nodo::new(None, blocks);
+
+                let mut out = Vec::new();
+                Markdown::render(&nodo, &mut out).unwrap();
+                let md = String::from_utf8(out).unwrap();
+
+                prop_assert_eq!(Markdown::parse(&md).unwrap(), nodo, "rendered as:\n{}", md);
+            }
+        }
+    }
 }
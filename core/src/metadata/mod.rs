@@ -0,0 +1,441 @@
+use thiserror::Error;
+
+/// The format that the front matter of a nodo was written in.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Format {
+    /// Front matter delimited by `---` lines.
+    Yaml,
+    /// Front matter delimited by `+++` lines.
+    Toml,
+}
+
+impl Format {
+    const fn delimiter(self) -> &'static str {
+        match self {
+            Self::Yaml => "---",
+            Self::Toml => "+++",
+        }
+    }
+}
+
+/// A single metadata value.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    /// A TOML date or datetime, kept in its textual form.
+    Datetime(String),
+    List(Vec<Self>),
+    Map(Vec<(String, Self)>),
+}
+
+impl Value {
+    /// The string content of this value, if it is a string or a datetime.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) | Self::Datetime(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_list(&self) -> Option<&[Self]> {
+        match self {
+            Self::List(l) => Some(l),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid yaml front matter: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("invalid toml front matter: {0}")]
+    TomlDe(#[from] toml::de::Error),
+
+    #[error("failed to write toml front matter: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+
+    #[error("unsupported key in front matter: {key}")]
+    UnsupportedKey { key: String },
+}
+
+/// The metadata of a nodo, read from its front matter.
+///
+/// Fields keep the order they were written in so that rendering doesn't shuffle them.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    format: Format,
+    fields: Vec<(String, Value)>,
+}
+
+impl Metadata {
+    #[must_use]
+    pub const fn new(format: Format) -> Self {
+        Self {
+            format,
+            fields: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn format(&self) -> Format {
+        self.format
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Set the value of a field, returning the previous value if there was one.
+    ///
+    /// New fields are added after the existing ones.
+    pub fn insert<K: Into<String>>(&mut self, key: K, value: Value) -> Option<Value> {
+        let key = key.into();
+        if let Some((_, v)) = self.fields.iter_mut().find(|(k, _)| *k == key) {
+            Some(std::mem::replace(v, value))
+        } else {
+            self.fields.push((key, value));
+            None
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let i = self.fields.iter().position(|(k, _)| k == key)?;
+        Some(self.fields.remove(i).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.fields.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    #[must_use]
+    pub fn title(&self) -> Option<&str> {
+        self.get("title").and_then(Value::as_str)
+    }
+
+    #[must_use]
+    pub fn status(&self) -> Option<&str> {
+        self.get("status").and_then(Value::as_str)
+    }
+
+    #[must_use]
+    pub fn created(&self) -> Option<&str> {
+        self.get("created").and_then(Value::as_str)
+    }
+
+    /// The tags of the nodo, given either as a list or as a single string.
+    #[must_use]
+    pub fn tags(&self) -> Vec<&str> {
+        match self.get("tags") {
+            Some(Value::List(l)) => l.iter().filter_map(Value::as_str).collect(),
+            Some(v) => v.as_str().into_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Split leading front matter off of `s`, returning the parsed metadata and the remaining
+    /// content.
+    ///
+    /// Content that only looks like front matter is left alone: the opening delimiter has to be
+    /// followed directly by the fields, and the fields have to parse, so rules around paragraphs
+    /// stay rules.
+    pub(crate) fn split(s: &str) -> Result<(Option<Self>, &str), Error> {
+        for &format in &[Format::Yaml, Format::Toml] {
+            if let Some((raw, rest)) = split_delimited(s, format) {
+                if let Some(metadata) = Self::parse(format, raw)? {
+                    return Ok((Some(metadata), rest));
+                }
+            }
+        }
+        Ok((None, s))
+    }
+
+    fn parse(format: Format, raw: &str) -> Result<Option<Self>, Error> {
+        let fields = match format {
+            Format::Yaml => match serde_yaml::from_str(raw) {
+                Ok(serde_yaml::Value::Mapping(m)) => from_yaml_mapping(m)?,
+                _ => return Ok(None),
+            },
+            Format::Toml => match toml::from_str(raw) {
+                Ok(table) => from_toml_table(table),
+                Err(_) => return Ok(None),
+            },
+        };
+        Ok(Some(Self { format, fields }))
+    }
+
+    /// Render the metadata in its canonical form, including the delimiters.
+    pub(crate) fn render(&self) -> Result<String, Error> {
+        let body = match self.format {
+            Format::Yaml => {
+                let s = serde_yaml::to_string(&serde_yaml::Value::Mapping(to_yaml_mapping(
+                    &self.fields,
+                )))?;
+                // the yaml serializer starts a document with its own delimiter
+                s.trim_start_matches("---\n").to_string()
+            }
+            Format::Toml => toml::to_string(&to_toml_table(&self.fields))?,
+        };
+        let body = body.trim_end();
+        let delimiter = self.format.delimiter();
+        if body.is_empty() {
+            // a blank line here would no longer read as front matter
+            return Ok(format!("{delimiter}\n{delimiter}\n"));
+        }
+        Ok(format!("{delimiter}\n{body}\n{delimiter}\n"))
+    }
+}
+
+fn split_delimited(s: &str, format: Format) -> Option<(&str, &str)> {
+    let delimiter = format.delimiter();
+    let mut lines = s.split_inclusive('\n');
+    if lines.next()?.trim_end() != delimiter {
+        return None;
+    }
+
+    let start = delimiter.len() + s[delimiter.len()..].find('\n')? + 1;
+    // a blank line after the delimiter reads as a rule rather than the start of front matter
+    if lines.clone().next()?.trim().is_empty() {
+        return None;
+    }
+    let mut end = start;
+    for line in lines {
+        let trimmed = line.trim_end();
+        if trimmed == delimiter || (format == Format::Yaml && trimmed == "...") {
+            return Some((&s[start..end], &s[end + line.len()..]));
+        }
+        end += line.len();
+    }
+    None
+}
+
+fn from_yaml_mapping(m: serde_yaml::Mapping) -> Result<Vec<(String, Value)>, Error> {
+    m.into_iter()
+        .map(|(k, v)| {
+            let key = match k {
+                serde_yaml::Value::String(s) => s,
+                serde_yaml::Value::Number(n) => n.to_string(),
+                serde_yaml::Value::Bool(b) => b.to_string(),
+                k => {
+                    return Err(Error::UnsupportedKey {
+                        key: format!("{k:?}"),
+                    })
+                }
+            };
+            Ok((key, from_yaml(v)?))
+        })
+        .collect()
+}
+
+fn from_yaml(v: serde_yaml::Value) -> Result<Value, Error> {
+    Ok(match v {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(b) => Value::Boolean(b),
+        serde_yaml::Value::Number(n) => n.as_i64().map_or_else(
+            || Value::Float(n.as_f64().unwrap_or_default()),
+            Value::Integer,
+        ),
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(s) => {
+            Value::List(s.into_iter().map(from_yaml).collect::<Result<_, _>>()?)
+        }
+        serde_yaml::Value::Mapping(m) => Value::Map(from_yaml_mapping(m)?),
+    })
+}
+
+fn to_yaml_mapping(fields: &[(String, Value)]) -> serde_yaml::Mapping {
+    fields
+        .iter()
+        .map(|(k, v)| (serde_yaml::Value::String(k.clone()), to_yaml(v)))
+        .collect()
+}
+
+fn to_yaml(v: &Value) -> serde_yaml::Value {
+    match v {
+        Value::Null => serde_yaml::Value::Null,
+        Value::Boolean(b) => serde_yaml::Value::Bool(*b),
+        Value::Integer(i) => serde_yaml::Value::Number((*i).into()),
+        Value::Float(f) => serde_yaml::Value::Number((*f).into()),
+        Value::String(s) | Value::Datetime(s) => serde_yaml::Value::String(s.clone()),
+        Value::List(l) => serde_yaml::Value::Sequence(l.iter().map(to_yaml).collect()),
+        Value::Map(m) => serde_yaml::Value::Mapping(to_yaml_mapping(m)),
+    }
+}
+
+fn from_toml_table(t: toml::value::Table) -> Vec<(String, Value)> {
+    t.into_iter().map(|(k, v)| (k, from_toml(v))).collect()
+}
+
+fn from_toml(v: toml::Value) -> Value {
+    match v {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Integer(i),
+        toml::Value::Float(f) => Value::Float(f),
+        toml::Value::Boolean(b) => Value::Boolean(b),
+        toml::Value::Datetime(d) => Value::Datetime(d.to_string()),
+        toml::Value::Array(a) => Value::List(a.into_iter().map(from_toml).collect()),
+        toml::Value::Table(t) => Value::Map(from_toml_table(t)),
+    }
+}
+
+fn to_toml_table(fields: &[(String, Value)]) -> toml::value::Table {
+    // toml has no null, so null fields are left out
+    fields
+        .iter()
+        .filter_map(|(k, v)| to_toml(v).map(|v| (k.clone(), v)))
+        .collect()
+}
+
+fn to_toml(v: &Value) -> Option<toml::Value> {
+    Some(match v {
+        Value::Null => return None,
+        Value::Boolean(b) => toml::Value::Boolean(*b),
+        Value::Integer(i) => toml::Value::Integer(*i),
+        Value::Float(f) => toml::Value::Float(*f),
+        Value::String(s) => toml::Value::String(s.clone()),
+        Value::Datetime(s) => s
+            .parse()
+            .map_or_else(|_| toml::Value::String(s.clone()), toml::Value::Datetime),
+        Value::List(l) => toml::Value::Array(l.iter().filter_map(to_toml).collect()),
+        Value::Map(m) => toml::Value::Table(to_toml_table(m)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn split_yaml() {
+        let (metadata, rest) = Metadata::split(
+            "---
+title: A note
+tags: [work, ideas]
+created: 2020-09-01
+---
+# content
+",
+        )
+        .unwrap();
+        let metadata = metadata.unwrap();
+
+        assert_eq!(metadata.format(), Format::Yaml);
+        assert_eq!(metadata.title(), Some("A note"));
+        assert_eq!(metadata.tags(), vec!["work", "ideas"]);
+        assert_eq!(metadata.created(), Some("2020-09-01"));
+        assert_eq!(rest, "# content\n");
+    }
+
+    #[test]
+    fn split_toml() {
+        let (metadata, rest) = Metadata::split(
+            "+++
+title = \"A note\"
+status = \"draft\"
+created = 2020-09-01
++++
+
+content
+",
+        )
+        .unwrap();
+        let metadata = metadata.unwrap();
+
+        assert_eq!(metadata.format(), Format::Toml);
+        assert_eq!(metadata.title(), Some("A note"));
+        assert_eq!(metadata.status(), Some("draft"));
+        assert_eq!(metadata.created(), Some("2020-09-01"));
+        assert_eq!(rest, "\ncontent\n");
+    }
+
+    #[test]
+    fn split_ignores_rules() {
+        let s = "---
+
+a paragraph between rules
+
+---
+";
+        let (metadata, rest) = Metadata::split(s).unwrap();
+
+        assert_eq!(metadata, None);
+        assert_eq!(rest, s);
+    }
+
+    #[test]
+    fn split_ignores_blank_line_after_delimiter() {
+        let s = "---
+
+status: ok
+
+---";
+        let (metadata, rest) = Metadata::split(s).unwrap();
+
+        assert_eq!(metadata, None);
+        assert_eq!(rest, s);
+    }
+
+    #[test]
+    fn split_ignores_invalid_fields() {
+        for s in &[
+            "---\nNote: see [x](y): here\n---\n",
+            "+++\nnot = toml = here\n+++\n",
+        ] {
+            let (metadata, rest) = Metadata::split(s).unwrap();
+
+            assert_eq!(metadata, None);
+            assert_eq!(&rest, s);
+        }
+    }
+
+    #[test]
+    fn render_keeps_field_order() {
+        let mut metadata = Metadata::new(Format::Yaml);
+        metadata.insert("title", Value::String("b".to_string()));
+        metadata.insert("archived", Value::Boolean(false));
+        metadata.insert("tags", Value::List(vec![Value::String("x".to_string())]));
+
+        assert_eq!(
+            metadata.render().unwrap(),
+            "---\ntitle: b\narchived: false\ntags:\n  - x\n---\n"
+        );
+    }
+}
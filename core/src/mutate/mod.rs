@@ -0,0 +1,655 @@
+//! Changes to the content of a nodo, for frontends that edit nodos without an editor.
+//!
+//! Each change keeps the nodo well formed, so it can always be rendered again.
+//!
+//! Headings are given by a path of heading texts, outermost first, where each heading has to be
+//! in the section of the one before it. Headings are matched ignoring case and formatting.
+
+use crate::{
+    query::plain_text,
+    visit::{walk_block_mut, walk_list_item_mut, VisitMut},
+    Block, BlockKind, Inline, ListItem, ListType, Nodo, TaskState,
+};
+use std::ops::Range;
+use thiserror::Error;
+
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum EditError {
+    #[error("no heading found at {0}")]
+    NoHeading(String),
+
+    #[error("no list item at index {0}")]
+    NoListItem(usize),
+
+    #[error("can't move a list item to within itself")]
+    MoveIntoItself,
+}
+
+impl Nodo {
+    /// Insert a block at `index` in the top level blocks, shifting those after it down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of top level blocks.
+    pub fn insert_block(&mut self, index: usize, block: Block) {
+        self.blocks.insert(index, block);
+    }
+
+    /// Remove the top level block at `index`, if there is one.
+    pub fn remove_block(&mut self, index: usize) -> Option<Block> {
+        (index < self.blocks.len()).then(|| self.blocks.remove(index))
+    }
+
+    /// Add a block to the end of the nodo, or to the end of the content directly under the
+    /// heading at the given path, before any subheadings.
+    ///
+    /// Any headings of the path that are missing are added to the end of the section they
+    /// belong in.
+    pub fn append(&mut self, heading: &[&str], block: Block) {
+        let end = self.section(heading).end;
+        self.blocks.insert(end, block);
+    }
+
+    /// Add an open task to the end of the nodo, or under the heading at the given path as with
+    /// [`Nodo::append`].
+    ///
+    /// The task joins the list that ends the section if there is one, so that it isn't written
+    /// as a separate list.
+    pub fn append_task(&mut self, heading: &[&str], text: Vec<Inline>) {
+        let section = self.section(heading);
+        let item = ListItem::new(
+            Some(TaskState::Open),
+            vec![BlockKind::Paragraph(text).into()],
+        );
+
+        if section.end > section.start {
+            if let BlockKind::List(_, items) = &mut self.blocks[section.end - 1].kind {
+                items.push(item);
+                return;
+            }
+        }
+        self.blocks.insert(
+            section.end,
+            BlockKind::List(ListType::Plain('-'), vec![item]).into(),
+        );
+    }
+
+    /// Set the state of the task at `index` in [`Nodo::tasks`], returning its previous state or
+    /// `None` if there is no task at that index.
+    pub fn set_task_state(&mut self, index: usize, state: TaskState) -> Option<TaskState> {
+        let mut setter = TaskSetter {
+            index,
+            state,
+            previous: None,
+        };
+        setter.visit_nodo_mut(self);
+        setter.previous
+    }
+
+    /// Move the list item at index `from` in [`Nodo::list_items`], along with anything nested
+    /// in it, to the position of the item at index `to`.
+    ///
+    /// The item goes before the item at `to` when moving up the nodo and after it when moving
+    /// down, so it can be moved to either end of a list. A list or quote left empty by the move
+    /// is removed, joining any lists either side of it that would otherwise be read back as one.
+    ///
+    /// # Errors
+    ///
+    /// Errors if either index is past the last list item or if `to` is nested within the item
+    /// being moved.
+    pub fn move_list_item(&mut self, from: usize, to: usize) -> Result<(), EditError> {
+        if to >= self.list_items().count() {
+            return Err(EditError::NoListItem(to));
+        }
+        let Some(item) = self.list_items().nth(from) else {
+            return Err(EditError::NoListItem(from));
+        };
+        if from == to {
+            return Ok(());
+        }
+
+        let nested = count_items(item.blocks());
+        if from < to && to <= from + nested {
+            return Err(EditError::MoveIntoItself);
+        }
+
+        let item = take_item(&mut self.blocks, &mut { from });
+        let (to, after) = if to > from {
+            (to - nested - 1, true)
+        } else {
+            (to, false)
+        };
+        put_item(&mut self.blocks, &mut { to }, &mut { item }, after);
+        Ok(())
+    }
+
+    /// Replace the text of the heading at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the nodo doesn't have the heading.
+    pub fn rename_heading(&mut self, heading: &[&str], text: Vec<Inline>) -> Result<(), EditError> {
+        let index = match self.find_heading(heading) {
+            Ok(index) if !heading.is_empty() => index,
+            _ => return Err(EditError::NoHeading(heading.join(" > "))),
+        };
+        if let BlockKind::Heading(_, _, inlines) = &mut self.blocks[index].kind {
+            *inlines = text;
+        }
+        Ok(())
+    }
+
+    /// The range of top level blocks between the heading at the given path and the next
+    /// heading, or all of the blocks for an empty path.
+    ///
+    /// Missing headings are added.
+    fn section(&mut self, heading: &[&str]) -> Range<usize> {
+        if heading.is_empty() {
+            return 0..self.blocks.len();
+        }
+
+        let start = match self.find_heading(heading) {
+            Ok(index) => index + 1,
+            Err(missing) => {
+                let mut level = missing.parent.map_or_else(
+                    || self.new_heading_level() - 1,
+                    |p| heading_level(&self.blocks[p]),
+                );
+                for (i, name) in heading[missing.depth..].iter().enumerate() {
+                    level = (level + 1).min(6);
+                    self.blocks.insert(
+                        missing.end + i,
+                        BlockKind::Heading(
+                            level,
+                            None,
+                            vec![Inline::Plain(name.trim().to_string())],
+                        )
+                        .into(),
+                    );
+                }
+                let end = missing.end + heading.len() - missing.depth;
+                return end..end;
+            }
+        };
+
+        let end = self.blocks[start..]
+            .iter()
+            .position(|b| matches!(b.kind, BlockKind::Heading(_, _, _)))
+            .map_or(self.blocks.len(), |i| start + i);
+        start..end
+    }
+
+    /// The index of the heading at the given path.
+    fn find_heading(&self, heading: &[&str]) -> Result<usize, MissingHeading> {
+        let mut range = 0..self.blocks.len();
+        let mut parent = None;
+        for (depth, name) in heading.iter().enumerate() {
+            let parent_level = parent.map_or(0, |p| heading_level(&self.blocks[p]));
+            let found = range.clone().find(|&i| match &self.blocks[i].kind {
+                BlockKind::Heading(level, _, inlines) => {
+                    *level > parent_level
+                        && plain_text(inlines).trim().eq_ignore_ascii_case(name.trim())
+                }
+                _ => false,
+            });
+            let Some(index) = found else {
+                return Err(MissingHeading {
+                    depth,
+                    parent,
+                    end: range.end,
+                });
+            };
+
+            // the section of a heading runs until the next heading at the same level or above
+            let level = heading_level(&self.blocks[index]);
+            let end = (index + 1..range.end)
+                .find(|&i| matches!(self.blocks[i].kind, BlockKind::Heading(l, _, _) if l <= level))
+                .unwrap_or(range.end);
+            range = index + 1..end;
+            parent = Some(index);
+        }
+        parent.ok_or(MissingHeading {
+            depth: 0,
+            parent: None,
+            end: self.blocks.len(),
+        })
+    }
+
+    /// The level for a new heading at the top of the nodo, matching the other sections.
+    ///
+    /// A lone level 1 heading is taken to be the title, so new headings go beneath it.
+    fn new_heading_level(&self) -> u32 {
+        let mut levels = self.blocks.iter().filter_map(|b| match b.kind {
+            BlockKind::Heading(level, _, _) => Some(level),
+            _ => None,
+        });
+        let Some(first) = levels.next() else {
+            return 1;
+        };
+        levels.min().unwrap_or(if first == 1 { 2 } else { first })
+    }
+}
+
+/// Where the headings of a path stop being found.
+struct MissingHeading {
+    /// The number of headings of the path that were found.
+    depth: usize,
+    /// The index of the last heading found.
+    parent: Option<usize>,
+    /// The end of the section of the last heading found, where the missing heading belongs.
+    end: usize,
+}
+
+const fn heading_level(block: &Block) -> u32 {
+    match block.kind {
+        BlockKind::Heading(level, _, _) => level,
+        _ => 0,
+    }
+}
+
+/// Finds the task at an index into the tasks of a nodo and sets its state.
+struct TaskSetter {
+    index: usize,
+    state: TaskState,
+    previous: Option<TaskState>,
+}
+
+impl TaskSetter {
+    /// Set the state of `task` if it is the task at the index, returning whether the search is
+    /// over.
+    const fn visit_task(&mut self, task: &mut Option<TaskState>) -> bool {
+        if self.previous.is_some() {
+            return true;
+        }
+        if let Some(state) = *task {
+            if self.index == 0 {
+                self.previous = Some(state);
+                *task = Some(self.state);
+                return true;
+            }
+            self.index -= 1;
+        }
+        false
+    }
+}
+
+impl VisitMut for TaskSetter {
+    fn visit_block_mut(&mut self, b: &mut Block) {
+        if let BlockKind::Heading(_, task, _) = &mut b.kind {
+            if self.visit_task(task) {
+                return;
+            }
+        }
+        walk_block_mut(self, b);
+    }
+
+    fn visit_list_item_mut(&mut self, i: &mut ListItem) {
+        if !self.visit_task(&mut i.task) {
+            walk_list_item_mut(self, i);
+        }
+    }
+}
+
+/// The number of list items nested in some blocks.
+fn count_items(blocks: &[Block]) -> usize {
+    blocks
+        .iter()
+        .map(|b| match &b.kind {
+            BlockKind::List(_, items) => items.iter().map(|i| 1 + count_items(&i.blocks)).sum(),
+            BlockKind::Quote(blocks) | BlockKind::FootnoteDefinition(_, blocks) => {
+                count_items(blocks)
+            }
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Remove the list item at `index` in document order, counting down `index` past the items
+/// before it, and remove any list or quote that leaves empty.
+fn take_item(blocks: &mut Vec<Block>, index: &mut usize) -> Option<ListItem> {
+    for b in 0..blocks.len() {
+        let item = match &mut blocks[b].kind {
+            BlockKind::List(_, items) => (0..items.len()).find_map(|i| {
+                if *index == 0 {
+                    return Some(items.remove(i));
+                }
+                *index -= 1;
+                take_item(&mut items[i].blocks, index)
+            }),
+            BlockKind::Quote(blocks) | BlockKind::FootnoteDefinition(_, blocks) => {
+                take_item(blocks, index)
+            }
+            _ => None,
+        };
+        if item.is_some() {
+            let empty = match &blocks[b].kind {
+                BlockKind::List(_, items) => items.is_empty(),
+                BlockKind::Quote(blocks) => blocks.is_empty(),
+                _ => false,
+            };
+            if empty {
+                remove_empty(blocks, b);
+            }
+            return item;
+        }
+    }
+    None
+}
+
+/// Remove the empty block at `index`, joining the lists either side of it if they would
+/// otherwise be read back as one.
+fn remove_empty(blocks: &mut Vec<Block>, index: usize) {
+    blocks.remove(index);
+    if index == 0 || index == blocks.len() {
+        return;
+    }
+    let joined = match (&blocks[index - 1].kind, &blocks[index].kind) {
+        (BlockKind::List(ListType::Plain(a), _), BlockKind::List(ListType::Plain(b), _))
+        | (
+            BlockKind::List(ListType::Numbered(_, a), _),
+            BlockKind::List(ListType::Numbered(_, b), _),
+        ) => a == b,
+        _ => false,
+    };
+    if joined {
+        if let BlockKind::List(_, rest) = blocks.remove(index).kind {
+            if let BlockKind::List(_, items) = &mut blocks[index - 1].kind {
+                items.extend(rest);
+            }
+        }
+    }
+}
+
+/// Put an item next to the list item at `index` in document order, counting down `index` as
+/// with [`take_item`].
+fn put_item(blocks: &mut [Block], index: &mut usize, item: &mut Option<ListItem>, after: bool) {
+    for b in blocks {
+        match &mut b.kind {
+            BlockKind::List(_, items) => {
+                for i in 0..items.len() {
+                    if *index == 0 {
+                        if let Some(item) = item.take() {
+                            items.insert(if after { i + 1 } else { i }, item);
+                        }
+                        return;
+                    }
+                    *index -= 1;
+                    put_item(&mut items[i].blocks, index, item, after);
+                    if item.is_none() {
+                        return;
+                    }
+                }
+            }
+            BlockKind::Quote(blocks) | BlockKind::FootnoteDefinition(_, blocks) => {
+                put_item(blocks, index, item, after);
+                if item.is_none() {
+                    return;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Markdown, Org, Parse, Render};
+    use pretty_assertions::assert_eq;
+
+    fn render(nodo: &Nodo) -> String {
+        let mut out = Vec::new();
+        Markdown::render(nodo, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn plain(s: &str) -> Vec<Inline> {
+        vec![Inline::Plain(s.to_string())]
+    }
+
+    #[test]
+    fn insert_and_remove_blocks() {
+        let mut nodo = Markdown::parse("# Title\n\nfirst\n").unwrap();
+
+        nodo.insert_block(1, BlockKind::Rule.into());
+        assert_eq!(
+            nodo.remove_block(2),
+            Some(BlockKind::Paragraph(plain("first")).into())
+        );
+        assert_eq!(nodo.remove_block(2), None);
+
+        assert_eq!(render(&nodo), "# Title\n\n---\n");
+    }
+
+    #[test]
+    fn append_to_sections() {
+        let mut nodo = Markdown::parse(
+            "# Project
+
+- [ ] existing
+
+## Notes
+
+a note
+
+### Old
+
+## Done
+",
+        )
+        .unwrap();
+
+        nodo.append_task(&["project"], plain("joins the list"));
+        nodo.append_task(&["Notes"], plain("starts a list"));
+        nodo.append(
+            &["Notes"],
+            BlockKind::Paragraph(plain("another note")).into(),
+        );
+        nodo.append(
+            &["Project", "Notes", "New"],
+            BlockKind::Paragraph(plain("new")).into(),
+        );
+        nodo.append_task(&["Ideas"], plain("in a new section"));
+        nodo.append(&[], BlockKind::Paragraph(plain("the end")).into());
+
+        assert_eq!(
+            render(&nodo),
+            "# Project
+
+- [ ] existing
+- [ ] joins the list
+
+## Notes
+
+a note
+
+- [ ] starts a list
+
+another note
+
+### Old
+
+### New
+
+new
+
+## Done
+
+## Ideas
+
+- [ ] in a new section
+
+the end
+"
+        );
+    }
+
+    #[test]
+    fn append_to_new_nodo() {
+        let mut nodo = Nodo::default();
+        nodo.append_task(&["Inbox", "Today"], plain("first"));
+        assert_eq!(render(&nodo), "# Inbox\n\n## Today\n\n- [ ] first\n");
+    }
+
+    #[test]
+    fn heading_paths_follow_sections() {
+        let mut nodo = Markdown::parse("# A\n\n## Notes\n\n# B\n").unwrap();
+
+        // B doesn't have a notes section, so one is added to it rather than using the one in A
+        nodo.append(&["B", "Notes"], BlockKind::Paragraph(plain("b")).into());
+
+        assert_eq!(render(&nodo), "# A\n\n## Notes\n\n# B\n\n## Notes\n\nb\n");
+    }
+
+    #[test]
+    fn set_task_state() {
+        let mut nodo = Markdown::parse(
+            "- [ ] parent
+    - [x] done child
+    - a note
+        - [x] nested under a note
+- [x] other
+
+> - [ ] quoted
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            nodo.set_task_state(4, TaskState::Done),
+            Some(TaskState::Open)
+        );
+        assert_eq!(
+            nodo.set_task_state(1, TaskState::Open),
+            Some(TaskState::Done)
+        );
+        assert_eq!(nodo.set_task_state(5, TaskState::Done), None);
+
+        let states = nodo.tasks().iter().map(|t| t.state).collect::<Vec<_>>();
+        assert_eq!(
+            states,
+            vec![
+                TaskState::Open,
+                TaskState::Open,
+                TaskState::Done,
+                TaskState::Done,
+                TaskState::Done,
+            ]
+        );
+    }
+
+    #[test]
+    fn set_headline_task_state() {
+        let mut nodo = Org::parse("* TODO headline\n- [ ] checkbox\n").unwrap();
+
+        assert_eq!(
+            nodo.set_task_state(0, TaskState::Done),
+            Some(TaskState::Open)
+        );
+        assert_eq!(
+            nodo.set_task_state(1, TaskState::Done),
+            Some(TaskState::Open)
+        );
+
+        let mut out = Vec::new();
+        Org::render(&nodo, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "* DONE headline\n\n- [X] checkbox\n"
+        );
+    }
+
+    #[test]
+    fn move_list_items() {
+        let md = "- one
+    - one a
+    - one b
+- two
+
+> - three
+> - four
+";
+        let mut nodo = Markdown::parse(md).unwrap();
+
+        // up, before the item
+        nodo.move_list_item(2, 0).unwrap();
+        nodo.move_list_item(5, 3).unwrap();
+        assert_eq!(
+            render(&nodo),
+            "- one b
+- one
+    - one a
+- four
+- two
+
+> - three
+"
+        );
+
+        // down, after the item, along with its nested items
+        nodo.move_list_item(1, 5).unwrap();
+        assert_eq!(
+            render(&nodo),
+            "- one b
+- four
+- two
+
+> - three
+> - one
+>     - one a
+"
+        );
+
+        assert_eq!(nodo.move_list_item(4, 5), Err(EditError::MoveIntoItself));
+        assert_eq!(nodo.move_list_item(0, 6), Err(EditError::NoListItem(6)));
+
+        // out of a nested list, which is removed once it is empty
+        nodo.move_list_item(5, 2).unwrap();
+        assert_eq!(
+            render(&nodo),
+            "- one b
+- four
+- one a
+- two
+
+> - three
+> - one
+"
+        );
+    }
+
+    #[test]
+    fn move_list_items_out_of_containers() {
+        let mut nodo = Markdown::parse(
+            "- one
+
+> > - two
+
+- three
+",
+        )
+        .unwrap();
+
+        // the quotes are left empty, and the lists either side of them are then joined
+        nodo.move_list_item(1, 0).unwrap();
+        assert_eq!(nodo.blocks().len(), 1);
+        assert_eq!(render(&nodo), "- two\n- one\n- three\n");
+    }
+
+    #[test]
+    fn rename_headings() {
+        let mut nodo = Markdown::parse("# Project\n\n## Todo\n").unwrap();
+
+        nodo.rename_heading(&["project", "todo"], plain("Tasks"))
+            .unwrap();
+        assert_eq!(
+            nodo.rename_heading(&["Todo"], plain("Tasks")),
+            Err(EditError::NoHeading("Todo".to_string()))
+        );
+        assert_eq!(
+            nodo.rename_heading(&[], plain("Tasks")),
+            Err(EditError::NoHeading(String::new()))
+        );
+
+        assert_eq!(render(&nodo), "# Project\n\n## Tasks\n");
+    }
+}
@@ -0,0 +1,1214 @@
+//! Nodos written in [org](https://orgmode.org) syntax.
+//!
+//! Org elements are mapped onto the same blocks and inlines as markdown, so that tasks and
+//! queries work the same for both and nodos can be converted between them:
+//!
+//! - headlines are headings, where a `TODO`, `DONE` or `CANCELLED` keyword makes the headline an
+//!   open, done or cancelled task, and any priority and tags are kept as part of the text
+//! - checkbox items such as `- [X] done` are tasks
+//! - `#+begin_src` and `#+begin_example` blocks are code blocks, `#+begin_quote` blocks are
+//!   quotes and `#+begin_export html` blocks are html
+//! - keywords such as `#+title: Notes` at the start of the file are the metadata of the nodo
+//! - anything else that has no counterpart, such as drawers and comments, is kept verbatim as
+//!   raw blocks
+//!
+//! Org has no way to escape markup, so text that looks like markup is read back as markup.
+
+use crate::{
+    metadata::{Format, Value},
+    visit::Visit,
+    Alignment, Block, BlockKind, Inline, Link, LinkType, ListItem, ListType, Metadata, Nodo, Parse,
+    Position, Render, Span, StringRenderer, TaskState,
+};
+use std::{
+    convert::{Infallible, TryFrom},
+    io,
+};
+use thiserror::Error;
+
+pub struct Org;
+
+#[derive(Error, Debug)]
+pub enum RenderError {
+    #[error("failed to write content: {0}")]
+    WriteFailure(#[from] io::Error),
+
+    #[error("metadata field {key} can't be written as an org keyword")]
+    UnsupportedMetadata { key: String },
+}
+
+/// The characters that can come before the opening marker of emphasis.
+const PRE_EMPHASIS: &str = "-({'\"";
+
+/// The characters that can come after the closing marker of emphasis.
+const POST_EMPHASIS: &str = "-.,:!?;'\")}[";
+
+/// A line of the source, possibly with some of its start already consumed by a container.
+#[derive(Debug, Copy, Clone)]
+struct Line<'a> {
+    text: &'a str,
+    start: Position,
+}
+
+impl Line<'_> {
+    fn indent(&self) -> usize {
+        self.text.len() - self.text.trim_start_matches([' ', '\t']).len()
+    }
+
+    fn is_blank(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+
+    /// This line without its first `n` bytes.
+    fn skip(self, n: usize) -> Self {
+        let n = n.min(self.text.len());
+        Self {
+            text: &self.text[n..],
+            start: Position {
+                offset: self.start.offset + n,
+                line: self.start.line,
+                column: self.start.column + self.text[..n].chars().count(),
+            },
+        }
+    }
+
+    /// This line without up to `n` bytes of indentation.
+    fn dedent(self, n: usize) -> Self {
+        self.skip(self.indent().min(n))
+    }
+
+    fn end(&self) -> Position {
+        Position {
+            offset: self.start.offset + self.text.len(),
+            line: self.start.line,
+            column: self.start.column + self.text.chars().count(),
+        }
+    }
+}
+
+fn lines(s: &str) -> Vec<Line<'_>> {
+    let mut offset = 0;
+    s.split('\n')
+        .enumerate()
+        .map(|(i, text)| {
+            let start = Position {
+                offset,
+                line: i + 1,
+                column: 1,
+            };
+            offset += text.len() + 1;
+            Line {
+                text: text.strip_suffix('\r').unwrap_or(text),
+                start,
+            }
+        })
+        .collect()
+}
+
+fn span(lines: &[Line]) -> Span {
+    match lines {
+        [first, .., last] => Span {
+            start: first.start,
+            end: last.end(),
+        },
+        [line] => Span {
+            start: line.start,
+            end: line.end(),
+        },
+        [] => Span::default(),
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    s.get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &s[prefix.len()..])
+}
+
+/// The keywords at the start of a file, along with the number of lines they took up.
+fn parse_keywords(lines: &[Line]) -> (Option<Metadata>, usize) {
+    let mut metadata = Metadata::new(Format::Yaml);
+    let mut consumed = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if line.is_blank() {
+            continue;
+        }
+        let Some((key, value)) = keyword(line.text) else {
+            break;
+        };
+        metadata.insert(key.to_lowercase(), Value::String(value.to_string()));
+        consumed = i + 1;
+    }
+
+    if metadata.is_empty() {
+        (None, 0)
+    } else {
+        (Some(metadata), consumed)
+    }
+}
+
+/// The key and value of a `#+key: value` line.
+fn keyword(text: &str) -> Option<(&str, &str)> {
+    let (key, value) = text.strip_prefix("#+")?.split_once(':')?;
+    let is_block = ["begin", "end"]
+        .iter()
+        .any(|b| strip_prefix_ignore_case(key, b).is_some());
+    let is_key = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    (is_key && !is_block).then_some((key, value.trim()))
+}
+
+fn parse_blocks(lines: &[Line]) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].is_blank() {
+            i += 1;
+            continue;
+        }
+        let (kind, len) = parse_block(&lines[i..]);
+        blocks.push(Block {
+            kind,
+            span: span(&lines[i..i + len]),
+        });
+        i += len;
+    }
+    blocks
+}
+
+/// Parse the block starting at the first line, which isn't blank, returning it along with the
+/// number of lines it took up.
+fn parse_block(lines: &[Line]) -> (BlockKind, usize) {
+    let first = lines[0];
+    let text = first.text.trim();
+
+    if let Some((level, title)) = headline(&first) {
+        let (task, title) = headline_task(title);
+        return (BlockKind::Heading(level, task, parse_inlines(title)), 1);
+    }
+    if let Some(block) = parse_greater_block(lines) {
+        return block;
+    }
+    if let Some((list_type, _)) = list_marker(&first) {
+        return parse_list(lines, list_type);
+    }
+    if is_rule(text) {
+        return (BlockKind::Rule, 1);
+    }
+    if text.starts_with('|') {
+        return parse_table(lines);
+    }
+    if let Some((label, width)) = footnote_definition(first.text) {
+        let len = lines
+            .iter()
+            .skip(1)
+            .position(|l| {
+                l.is_blank() || headline(l).is_some() || footnote_definition(l.text).is_some()
+            })
+            .map_or(lines.len(), |p| p + 1);
+        let mut content = vec![first.skip(width)];
+        content.extend_from_slice(&lines[1..len]);
+        return (
+            BlockKind::FootnoteDefinition(label, parse_blocks(&content)),
+            len,
+        );
+    }
+    if let Some(len) = drawer(lines) {
+        return (BlockKind::Raw(source(&lines[..len])), len);
+    }
+    if is_comment(text) {
+        return (BlockKind::Raw(first.text.to_string()), 1);
+    }
+
+    let len = lines
+        .iter()
+        .skip(1)
+        .position(|l| l.is_blank() || starts_block(l))
+        .map_or(lines.len(), |p| p + 1);
+    let text = lines[..len]
+        .iter()
+        .map(|l| l.text.trim())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (BlockKind::Paragraph(parse_inlines(&text)), len)
+}
+
+/// The text of lines as they were written, after any container prefix.
+fn source(lines: &[Line]) -> String {
+    lines.iter().map(|l| l.text).collect::<Vec<_>>().join("\n")
+}
+
+/// Whether a line starts a block that ends a paragraph before it.
+fn starts_block(line: &Line) -> bool {
+    let trimmed = line.text.trim();
+    headline(line).is_some()
+        || list_marker(line).is_some()
+        || is_rule(trimmed)
+        || trimmed.starts_with('|')
+        || footnote_definition(line.text).is_some()
+        || is_comment(trimmed)
+}
+
+/// The level and title of a headline such as `** Title`, which has to start the line in the
+/// source rather than only within a container.
+fn headline<'a>(line: &Line<'a>) -> Option<(u32, &'a str)> {
+    let title = line.text.trim_start_matches('*');
+    let level = line.text.len() - title.len();
+    if level == 0 || line.start.column > 1 || !(title.is_empty() || title.starts_with(' ')) {
+        return None;
+    }
+    Some((u32::try_from(level).ok()?, title.trim()))
+}
+
+/// Split the `TODO`, `DONE` or `CANCELLED` keyword off of the title of a headline.
+fn headline_task(title: &str) -> (Option<TaskState>, &str) {
+    let (keyword, rest) = title.split_once(' ').unwrap_or((title, ""));
+    match keyword {
+        "TODO" => (Some(TaskState::Open), rest.trim_start()),
+        "DONE" => (Some(TaskState::Done), rest.trim_start()),
+        "CANCELLED" => (Some(TaskState::Cancelled), rest.trim_start()),
+        _ => (None, title),
+    }
+}
+
+/// The keyword for a headline task.
+///
+/// Org only has `TODO` and `DONE` by default, so other open states are written as `TODO`.
+/// `CANCELLED` is a common addition to those, which keeps cancelled tasks apart from done ones.
+const fn headline_keyword(state: TaskState) -> &'static str {
+    match state {
+        TaskState::Done => "DONE",
+        TaskState::Cancelled => "CANCELLED",
+        TaskState::Open | TaskState::InProgress | TaskState::Deferred => "TODO",
+    }
+}
+
+fn is_rule(text: &str) -> bool {
+    text.len() >= 5 && text.chars().all(|c| c == '-')
+}
+
+/// Whether a line is a comment or a keyword, neither of which has a counterpart in a nodo.
+fn is_comment(text: &str) -> bool {
+    text == "#" || text.starts_with("# ") || text.starts_with("#+")
+}
+
+/// The number of lines taken up by a drawer, such as `:PROPERTIES:` up to `:END:`.
+fn drawer(lines: &[Line]) -> Option<usize> {
+    let name = lines[0].text.trim().strip_prefix(':')?.strip_suffix(':')?;
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return None;
+    }
+    let end = lines
+        .iter()
+        .position(|l| l.text.trim().eq_ignore_ascii_case(":end:"))?;
+    Some(end + 1)
+}
+
+/// Parse a `#+begin_name` block, if it has a matching end.
+fn parse_greater_block(lines: &[Line]) -> Option<(BlockKind, usize)> {
+    let first = lines[0];
+    let rest = strip_prefix_ignore_case(first.text.trim(), "#+begin_")?;
+    let (name, args) = rest.split_once(' ').unwrap_or((rest, ""));
+    let args = args.trim();
+    let end_line = format!("#+end_{name}");
+    let end = lines
+        .iter()
+        .position(|l| l.text.trim().eq_ignore_ascii_case(&end_line))?;
+
+    // content is indented relative to the begin line
+    let content = lines[1..end]
+        .iter()
+        .map(|l| l.dedent(first.indent()))
+        .collect::<Vec<_>>();
+    let code = || {
+        content
+            .iter()
+            .map(|l| unescape(l.text) + "\n")
+            .collect::<String>()
+    };
+    let kind = match name.to_lowercase().as_str() {
+        "src" => BlockKind::Code(args.to_string(), code()),
+        "example" => BlockKind::Code(String::new(), code()),
+        "quote" => BlockKind::Quote(parse_blocks(&content)),
+        "export" if args.eq_ignore_ascii_case("html") => BlockKind::Html(source(&content)),
+        _ => BlockKind::Raw(source(&lines[..=end])),
+    };
+    Some((kind, end + 1))
+}
+
+/// The type of list started by a line and the width of its marker, including any indentation.
+fn list_marker(line: &Line) -> Option<(ListType, usize)> {
+    let trimmed = line.text.trim_start_matches([' ', '\t']);
+    let indent = line.indent();
+    let digits = trimmed.len()
+        - trimmed
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .len();
+
+    let (list_type, width) = match trimmed.chars().next()? {
+        // a star at the start of a line is a headline
+        '*' if indent == 0 && line.start.column == 1 => return None,
+        c @ ('-' | '+' | '*') => (ListType::Plain(c), 1),
+        _ if (1..10).contains(&digits) => {
+            let delimiter = trimmed[digits..]
+                .chars()
+                .next()
+                .filter(|&c| c == '.' || c == ')')?;
+            (
+                ListType::Numbered(trimmed[..digits].parse().ok()?, delimiter),
+                digits + 1,
+            )
+        }
+        _ => return None,
+    };
+    match trimmed[width..].chars().next() {
+        None => Some((list_type, indent + width)),
+        Some(' ') => Some((list_type, indent + width + 1)),
+        Some(_) => None,
+    }
+}
+
+/// The state of a task from a checkbox such as `[X] ` at the start of an item, along with its
+/// width.
+fn checkbox(text: &str) -> Option<(TaskState, usize)> {
+    let mut chars = text.chars();
+    if chars.next() != Some('[') {
+        return None;
+    }
+    let state = TaskState::from_marker(chars.next()?)?;
+    if chars.next() != Some(']') {
+        return None;
+    }
+    match chars.next() {
+        None => Some((state, 3)),
+        Some(' ') => Some((state, 4)),
+        Some(_) => None,
+    }
+}
+
+fn parse_list(lines: &[Line], list_type: ListType) -> (BlockKind, usize) {
+    let indent = lines[0].indent();
+    let mut items = Vec::new();
+    let mut i = 0;
+    // items of the same list are indented the same and use the same kind of marker
+    let item_marker = |line: &Line| {
+        list_marker(line).filter(|(t, _)| {
+            line.indent() == indent
+                && match (t, list_type) {
+                    (ListType::Plain(a), ListType::Plain(b))
+                    | (ListType::Numbered(_, a), ListType::Numbered(_, b)) => *a == b,
+                    _ => false,
+                }
+        })
+    };
+    while let Some((_, width)) = lines.get(i).and_then(item_marker) {
+        // an item continues over lines indented past its bullet, including blank lines between
+        // them
+        let mut end = i + 1;
+        let mut next = i + 1;
+        while let Some(line) = lines.get(next) {
+            if !line.is_blank() {
+                if line.indent() <= indent {
+                    break;
+                }
+                end = next + 1;
+            }
+            next += 1;
+        }
+
+        let first = lines[i].skip(width);
+        let (task, first) = match checkbox(first.text) {
+            Some((state, width)) => (Some(state), first.skip(width)),
+            None => (None, first),
+        };
+        let mut content = vec![first];
+        content.extend(lines[i + 1..end].iter().map(|l| l.dedent(width)));
+        items.push(ListItem {
+            task,
+            blocks: parse_blocks(&content),
+            span: span(&lines[i..end]),
+        });
+
+        let continues = lines.get(next).and_then(item_marker).is_some();
+        i = if continues { next } else { end };
+    }
+    (BlockKind::List(list_type, items), i)
+}
+
+fn parse_table(lines: &[Line]) -> (BlockKind, usize) {
+    let len = lines
+        .iter()
+        .position(|l| !l.text.trim().starts_with('|'))
+        .unwrap_or(lines.len());
+
+    let mut rows = lines[..len]
+        .iter()
+        .map(|l| l.text.trim())
+        .filter(|t| !t.starts_with("|-"))
+        .map(|t| {
+            let t = t.strip_prefix('|').unwrap_or(t);
+            let t = t.strip_suffix('|').unwrap_or(t);
+            t.split('|')
+                .map(|c| parse_inlines(c.trim()))
+                .collect::<Vec<_>>()
+        });
+    let header = rows.next().unwrap_or_default();
+    let rows = rows.collect::<Vec<_>>();
+    let columns = rows.iter().map(Vec::len).fold(header.len(), usize::max);
+    (
+        BlockKind::Table(vec![Alignment::None; columns], header, rows),
+        len,
+    )
+}
+
+/// The label of a footnote definition such as `[fn:1] text`, along with its width.
+fn footnote_definition(text: &str) -> Option<(String, usize)> {
+    let (inline, width) = footnote_reference(text)?;
+    let Inline::FootnoteReference(label) = inline else {
+        return None;
+    };
+    match text[width..].chars().next() {
+        None => Some((label, width)),
+        Some(' ') => Some((label, width + 1)),
+        Some(_) => None,
+    }
+}
+
+/// Remove the comma that protects a line of code from being read as org syntax.
+fn unescape(line: &str) -> String {
+    let content = line.trim_start_matches([' ', '\t']);
+    let indent = &line[..line.len() - content.len()];
+    match content.strip_prefix(',') {
+        Some(rest) if needs_escape(rest) => format!("{indent}{rest}"),
+        _ => line.to_string(),
+    }
+}
+
+/// Whether a line of code, without its indentation, would be read as org syntax.
+fn needs_escape(content: &str) -> bool {
+    let content = content.trim_start_matches(',');
+    content.starts_with('*') || content.starts_with("#+")
+}
+
+fn parse_inlines(s: &str) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    let mut plain = String::new();
+    let mut i = 0;
+    while let Some(c) = s[i..].chars().next() {
+        let rest = &s[i..];
+        let parsed = match c {
+            '\n' if plain.ends_with("\\\\") => {
+                plain.truncate(plain.len() - 2);
+                plain.truncate(plain.trim_end().len());
+                Some((Inline::HardBreak, 1))
+            }
+            '\n' => Some((Inline::SoftBreak, 1)),
+            '[' => link(rest).or_else(|| footnote_reference(rest)),
+            '@' => export_snippet(rest),
+            '*' | '/' | '+' | '~' | '=' => emphasis(rest, s[..i].chars().next_back()),
+            _ => None,
+        };
+
+        if let Some((inline, len)) = parsed {
+            if !plain.is_empty() {
+                inlines.push(Inline::Plain(std::mem::take(&mut plain)));
+            }
+            inlines.push(inline);
+            i += len;
+        } else {
+            plain.push(c);
+            i += c.len_utf8();
+        }
+    }
+    if !plain.is_empty() {
+        inlines.push(Inline::Plain(plain));
+    }
+    inlines
+}
+
+/// Emphasis starting with its marker, given the character before it.
+fn emphasis(s: &str, previous: Option<char>) -> Option<(Inline, usize)> {
+    let marker = s.chars().next()?;
+    if !previous.is_none_or(|c| c.is_whitespace() || PRE_EMPHASIS.contains(c))
+        || s[1..].chars().next().is_none_or(char::is_whitespace)
+    {
+        return None;
+    }
+
+    let mut last = marker;
+    let mut newlines = 0;
+    for (i, c) in s.char_indices().skip(1) {
+        if c == '\n' {
+            // emphasis can span at most two lines
+            newlines += 1;
+            if newlines > 1 {
+                return None;
+            }
+        }
+        let closes = c == marker
+            && i > 1
+            && !last.is_whitespace()
+            && s[i + 1..]
+                .chars()
+                .next()
+                .is_none_or(|c| c.is_whitespace() || POST_EMPHASIS.contains(c));
+        if closes {
+            let content = &s[1..i];
+            let inline = match marker {
+                '*' => Inline::Strong(parse_inlines(content)),
+                '/' => Inline::Emph(parse_inlines(content)),
+                '+' => Inline::Strikethrough(parse_inlines(content)),
+                _ => Inline::Code(content.to_string()),
+            };
+            return Some((inline, i + 1));
+        }
+        last = c;
+    }
+    None
+}
+
+/// A link such as `[[destination][text]]` or `[[destination]]`.
+fn link(s: &str) -> Option<(Inline, usize)> {
+    let inner = s.strip_prefix("[[")?;
+    let end = inner.find("]]")?;
+    let (destination, text) = inner[..end]
+        .split_once("][")
+        .unwrap_or((&inner[..end], &inner[..end]));
+    if destination.is_empty() || destination.contains('\n') {
+        return None;
+    }
+    let link = Link {
+        link_type: LinkType::Inline,
+        text: text.replace('\n', " "),
+        destination: destination.to_string(),
+        title: String::new(),
+    };
+    Some((Inline::Link(link), end + 4))
+}
+
+/// A footnote reference such as `[fn:1]`.
+fn footnote_reference(s: &str) -> Option<(Inline, usize)> {
+    let label = s.strip_prefix("[fn:")?;
+    let end = label.find(']')?;
+    let label = &label[..end];
+    if label.is_empty()
+        || !label
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        return None;
+    }
+    Some((Inline::FootnoteReference(label.to_string()), end + 5))
+}
+
+/// An html export snippet such as `@@html:<br>@@`.
+fn export_snippet(s: &str) -> Option<(Inline, usize)> {
+    let content = s.strip_prefix("@@html:")?;
+    let end = content.find("@@")?;
+    Some((Inline::Html(content[..end].to_string()), end + 9))
+}
+
+impl Parse for Org {
+    /// Any text is valid org, so parsing never fails.
+    type ParseError = Infallible;
+
+    fn parse(s: &str) -> Result<Nodo, Self::ParseError> {
+        let lines = lines(s);
+        let (metadata, len) = parse_keywords(&lines);
+        Ok(Nodo::new(metadata, parse_blocks(&lines[len..])))
+    }
+}
+
+struct Renderer {
+    out: String,
+    prefix: String,
+}
+
+impl StringRenderer for Renderer {
+    fn out(&mut self) -> &mut String {
+        &mut self.out
+    }
+}
+
+impl Renderer {
+    const fn new() -> Self {
+        Self {
+            out: String::new(),
+            prefix: String::new(),
+        }
+    }
+
+    /// Run `f` with `suffix` added to the prefix of each new line.
+    fn indented<F: FnOnce(&mut Self)>(&mut self, suffix: &str, f: F) {
+        let prefix_len = self.prefix.len();
+        self.prefix.push_str(suffix);
+        f(self);
+        self.prefix.truncate(prefix_len);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        self.out.push_str(&self.prefix);
+    }
+
+    fn blank_line(&mut self) {
+        self.out.push('\n');
+        self.out.push_str(self.prefix.trim_end());
+        self.newline();
+    }
+
+    /// Write lines of text, the first of which continues the current line.
+    fn write_lines(&mut self, s: &str) {
+        for (i, line) in s.lines().enumerate() {
+            if i > 0 && line.is_empty() {
+                self.out.push('\n');
+                self.out.push_str(self.prefix.trim_end());
+            } else {
+                if i > 0 {
+                    self.newline();
+                }
+                self.out.push_str(line);
+            }
+        }
+    }
+
+    fn render_code_block(&mut self, lang: &str, content: &str) {
+        let name = if lang.is_empty() {
+            self.out.push_str("#+begin_example");
+            "example"
+        } else {
+            self.out.push_str("#+begin_src ");
+            self.out.push_str(lang);
+            "src"
+        };
+        for line in content.lines() {
+            if line.is_empty() {
+                self.out.push('\n');
+                self.out.push_str(self.prefix.trim_end());
+                continue;
+            }
+            self.newline();
+            let content = line.trim_start_matches([' ', '\t']);
+            if needs_escape(content) {
+                self.out.push_str(&line[..line.len() - content.len()]);
+                self.out.push(',');
+                self.out.push_str(content);
+            } else {
+                self.out.push_str(line);
+            }
+        }
+        self.newline();
+        self.out.push_str("#+end_");
+        self.out.push_str(name);
+    }
+
+    fn render_list_items(&mut self, list_type: ListType, items: &[ListItem]) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.newline();
+            }
+            let marker = match list_type {
+                ListType::Numbered(start, delimiter) => {
+                    format!("{}{} ", start + i as u64, delimiter)
+                }
+                ListType::Plain('+') => "+ ".to_string(),
+                // a star bullet is only allowed when indented, so dashes are used throughout
+                ListType::Plain(_) => "- ".to_string(),
+            };
+            self.out.push_str(&marker);
+            self.indented(&" ".repeat(marker.len()), |r| r.visit_list_item(item));
+        }
+    }
+
+    fn render_table(&mut self, header: &[Vec<Inline>], rows: &[Vec<Vec<Inline>>]) {
+        let render_row = |row: &[Vec<Inline>]| {
+            row.iter()
+                .map(|cell| {
+                    let mut r = Self::new();
+                    r.visit_inlines(cell);
+                    r.out
+                })
+                .collect::<Vec<_>>()
+        };
+        let header = render_row(header);
+        let rows = rows.iter().map(|r| render_row(r)).collect::<Vec<_>>();
+
+        let columns = rows.iter().map(Vec::len).fold(header.len(), usize::max);
+        let widths = (0..columns)
+            .map(|c| {
+                std::iter::once(&header)
+                    .chain(&rows)
+                    .filter_map(|r| r.get(c))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+
+        let line = |row: &[String]| {
+            let cells = widths
+                .iter()
+                .enumerate()
+                .map(|(c, w)| {
+                    let cell = row.get(c).map_or("", String::as_str);
+                    format!(" {}{} ", cell, " ".repeat(w - cell.chars().count()))
+                })
+                .collect::<Vec<_>>();
+            format!("|{}|", cells.join("|"))
+        };
+        self.out.push_str(&line(&header));
+        self.newline();
+        let separator = widths.iter().map(|w| "-".repeat(w + 2)).collect::<Vec<_>>();
+        write!(self, "|{}|", separator.join("+"));
+        for row in &rows {
+            self.newline();
+            self.out.push_str(&line(row));
+        }
+    }
+}
+
+impl<'a> Visit<'a> for Renderer {
+    fn visit_blocks(&mut self, bs: &'a [Block]) {
+        // links are written with their destinations, so reference definitions aren't needed
+        let bs = bs
+            .iter()
+            .filter(|b| !matches!(b.kind, BlockKind::ReferenceDefinition(_, _, _)))
+            .collect::<Vec<_>>();
+        for (i, b) in bs.iter().enumerate() {
+            if i > 0 {
+                self.blank_line();
+            }
+            self.visit_block(b);
+        }
+    }
+
+    fn visit_block(&mut self, b: &'a Block) {
+        match &b.kind {
+            BlockKind::Paragraph(inlines) => self.visit_inlines(inlines),
+            BlockKind::Heading(level, task, inlines) => {
+                self.out.push_str(&"*".repeat(*level as usize));
+                self.out.push(' ');
+                if let Some(state) = task {
+                    self.out.push_str(headline_keyword(*state));
+                    if !inlines.is_empty() {
+                        self.out.push(' ');
+                    }
+                }
+                self.visit_inlines(inlines);
+            }
+            BlockKind::Code(lang, content) => self.render_code_block(lang, content),
+            BlockKind::Quote(blocks) => {
+                self.out.push_str("#+begin_quote");
+                self.newline();
+                self.visit_blocks(blocks);
+                self.newline();
+                self.out.push_str("#+end_quote");
+            }
+            BlockKind::List(list_type, items) => self.render_list_items(*list_type, items),
+            BlockKind::Table(_, header, rows) => self.render_table(header, rows),
+            BlockKind::FootnoteDefinition(label, blocks) => {
+                write!(self, "[fn:{label}] ");
+                self.visit_blocks(blocks);
+            }
+            BlockKind::Html(html) => {
+                self.out.push_str("#+begin_export html");
+                self.newline();
+                self.write_lines(html);
+                self.newline();
+                self.out.push_str("#+end_export");
+            }
+            BlockKind::ReferenceDefinition(_, _, _) => {}
+            BlockKind::Rule => self.out.push_str("-----"),
+            BlockKind::Raw(s) => self.write_lines(s),
+        }
+    }
+
+    fn visit_list_item(&mut self, item: &'a ListItem) {
+        if let Some(state) = item.task {
+            // org writes done checkboxes with a capital
+            let marker = match state {
+                TaskState::Done => 'X',
+                TaskState::Open
+                | TaskState::InProgress
+                | TaskState::Cancelled
+                | TaskState::Deferred => state.marker(),
+            };
+            write!(self, "[{marker}] ");
+        }
+
+        // nested lists follow straight on from the text of the item
+        for (i, b) in item.blocks.iter().enumerate() {
+            match b.kind {
+                _ if i == 0 => {}
+                BlockKind::List(_, _) => self.newline(),
+                _ => self.blank_line(),
+            }
+            self.visit_block(b);
+        }
+    }
+
+    fn visit_inline(&mut self, i: &'a Inline) {
+        let wrapped = |r: &mut Self, marker: char, inlines: &'a [Inline]| {
+            r.out.push(marker);
+            r.visit_inlines(inlines);
+            r.out.push(marker);
+        };
+        match i {
+            Inline::Plain(s) | Inline::Raw(s) => self.out.push_str(s),
+            Inline::Emph(inlines) => wrapped(self, '/', inlines),
+            Inline::Strong(inlines) => wrapped(self, '*', inlines),
+            Inline::Strikethrough(inlines) => wrapped(self, '+', inlines),
+            Inline::Code(s) => {
+                let marker = if s.contains('~') && !s.contains('=') {
+                    '='
+                } else {
+                    '~'
+                };
+                write!(self, "{marker}{s}{marker}");
+            }
+            Inline::Link(link) | Inline::Image(link) => {
+                if link.text.is_empty() || link.text == link.destination {
+                    write!(self, "[[{}]]", link.destination);
+                } else {
+                    write!(self, "[[{}][{}]]", link.destination, link.text);
+                }
+            }
+            Inline::Html(s) => write!(self, "@@html:{s}@@"),
+            Inline::FootnoteReference(label) => write!(self, "[fn:{label}]"),
+            Inline::SoftBreak => self.newline(),
+            Inline::HardBreak => {
+                self.out.push_str("\\\\");
+                self.newline();
+            }
+        }
+    }
+}
+
+/// The value of a metadata field as the value of an org keyword.
+fn keyword_value(key: &str, value: &Value) -> Result<String, RenderError> {
+    Ok(match value {
+        Value::Null => String::new(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::String(s) | Value::Datetime(s) if !s.contains('\n') => s.clone(),
+        // lists of words, such as tags, are written separated by spaces
+        Value::List(values) => values
+            .iter()
+            .map(|v| match v {
+                Value::List(_) | Value::Map(_) => Err(RenderError::UnsupportedMetadata {
+                    key: key.to_string(),
+                }),
+                v => keyword_value(key, v),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .join(" "),
+        Value::String(_) | Value::Datetime(_) | Value::Map(_) => {
+            return Err(RenderError::UnsupportedMetadata {
+                key: key.to_string(),
+            })
+        }
+    })
+}
+
+impl Render for Org {
+    type RenderError = RenderError;
+
+    fn render<W: std::io::Write>(n: &Nodo, w: &mut W) -> Result<(), Self::RenderError> {
+        let mut renderer = Renderer::new();
+        if let Some(metadata) = n.metadata.as_ref() {
+            for (key, value) in metadata.iter() {
+                let value = keyword_value(key, value)?;
+                renderer
+                    .out
+                    .push_str(format!("#+{key}: {value}").trim_end());
+                renderer.out.push('\n');
+            }
+            if !metadata.is_empty() && !n.blocks.is_empty() {
+                renderer.out.push('\n');
+            }
+        }
+
+        renderer.visit_blocks(&n.blocks);
+        if !n.blocks.is_empty() {
+            renderer.out.push('\n');
+        }
+        w.write_all(renderer.out.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const NOTES: &str = "#+title: Notes
+#+filetags: work
+
+* TODO Plan the /launch/ [#A] :work:
+
+Some *bold*, /emphasised/, +struck+ and ~code~ text with a [[https://example.com][link]]
+and a footnote[fn:1].
+
+- [X] write the draft
+- [-] review it
+  - [ ] with the team
+  - [~] with everyone
+- a plain item
+
+  with a second paragraph
+
+1. first
+2. second
+
+** Code
+
+#+begin_src rust
+fn main() {
+,* not a headline
+}
+#+end_src
+
+#+begin_quote
+Quoted *text*.
+#+end_quote
+
+| Name | Done |
+|------+------|
+| a    | yes  |
+
+-----
+
+:PROPERTIES:
+:ID: 1234
+:END:
+
+[fn:1] The footnote.
+";
+
+    fn render(n: &Nodo) -> String {
+        let mut out = Vec::new();
+        Org::render(n, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn plain(s: &str) -> Inline {
+        Inline::Plain(s.to_string())
+    }
+
+    fn paragraph(s: &str) -> Block {
+        BlockKind::Paragraph(vec![plain(s)]).into()
+    }
+
+    #[test]
+    fn parse_org_inlines() {
+        let n = Org::parse(NOTES).unwrap();
+
+        assert_eq!(n.metadata().and_then(Metadata::title), Some("Notes"));
+        let blocks = n.blocks();
+        assert_eq!(
+            blocks[0].kind(),
+            &BlockKind::Heading(
+                1,
+                Some(TaskState::Open),
+                vec![
+                    plain("Plan the "),
+                    Inline::Emph(vec![plain("launch")]),
+                    plain(" [#A] :work:"),
+                ]
+            )
+        );
+        assert_eq!(
+            blocks[1].kind(),
+            &BlockKind::Paragraph(vec![
+                plain("Some "),
+                Inline::Strong(vec![plain("bold")]),
+                plain(", "),
+                Inline::Emph(vec![plain("emphasised")]),
+                plain(", "),
+                Inline::Strikethrough(vec![plain("struck")]),
+                plain(" and "),
+                Inline::Code("code".to_string()),
+                plain(" text with a "),
+                Inline::Link(Link {
+                    link_type: LinkType::Inline,
+                    text: "link".to_string(),
+                    destination: "https://example.com".to_string(),
+                    title: String::new(),
+                }),
+                Inline::SoftBreak,
+                plain("and a footnote"),
+                Inline::FootnoteReference("1".to_string()),
+                plain("."),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_org_blocks() {
+        let n = Org::parse(NOTES).unwrap();
+
+        let blocks = n.blocks();
+        assert_eq!(
+            blocks[2].kind(),
+            &BlockKind::List(
+                ListType::Plain('-'),
+                vec![
+                    ListItem::new(Some(TaskState::Done), vec![paragraph("write the draft")]),
+                    ListItem::new(
+                        Some(TaskState::InProgress),
+                        vec![
+                            paragraph("review it"),
+                            BlockKind::List(
+                                ListType::Plain('-'),
+                                vec![
+                                    ListItem::new(
+                                        Some(TaskState::Open),
+                                        vec![paragraph("with the team")]
+                                    ),
+                                    ListItem::new(
+                                        Some(TaskState::Cancelled),
+                                        vec![paragraph("with everyone")]
+                                    ),
+                                ]
+                            )
+                            .into(),
+                        ]
+                    ),
+                    ListItem::new(
+                        None,
+                        vec![
+                            paragraph("a plain item"),
+                            paragraph("with a second paragraph")
+                        ]
+                    ),
+                ]
+            )
+        );
+        assert_eq!(
+            blocks[3].kind(),
+            &BlockKind::List(
+                ListType::Numbered(1, '.'),
+                vec![
+                    ListItem::new(None, vec![paragraph("first")]),
+                    ListItem::new(None, vec![paragraph("second")]),
+                ]
+            )
+        );
+        assert_eq!(
+            blocks[5].kind(),
+            &BlockKind::Code(
+                "rust".to_string(),
+                "fn main() {\n* not a headline\n}\n".to_string()
+            )
+        );
+        assert_eq!(
+            blocks[6].kind(),
+            &BlockKind::Quote(vec![BlockKind::Paragraph(vec![
+                plain("Quoted "),
+                Inline::Strong(vec![plain("text")]),
+                plain("."),
+            ])
+            .into()])
+        );
+        assert_eq!(
+            blocks[9].kind(),
+            &BlockKind::Raw(":PROPERTIES:\n:ID: 1234\n:END:".to_string())
+        );
+        assert_eq!(
+            blocks[10].kind(),
+            &BlockKind::FootnoteDefinition("1".to_string(), vec![paragraph("The footnote.")])
+        );
+
+        let count = n.count_tasks();
+        std::assert_eq!((count.completed, count.in_progress, count.total), (1, 1, 4));
+        let lines = n.tasks().iter().map(|t| t.line).collect::<Vec<_>>();
+        assert_eq!(lines, vec![4, 9, 10, 11, 12]);
+    }
+
+    #[test]
+    fn render_org() {
+        assert_eq!(render(&Org::parse(NOTES).unwrap()), NOTES);
+
+        let n = Org::parse("- [x] done\n  * nested with ~a~b~\n\n\n+ other\n").unwrap();
+        assert_eq!(render(&n), "- [X] done\n  - nested with =a~b=\n\n+ other\n");
+    }
+
+    #[test]
+    fn parse_and_write_headline_tasks() {
+        let org = "* TODO open\n\n* DONE done\n\n* CANCELLED dropped\n\n* TODOS not a task\n";
+        let n = Org::parse(org).unwrap();
+
+        let states = n
+            .blocks()
+            .iter()
+            .map(|b| match b.kind() {
+                BlockKind::Heading(_, task, _) => *task,
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        std::assert_eq!(
+            states,
+            vec![
+                Some(TaskState::Open),
+                Some(TaskState::Done),
+                Some(TaskState::Cancelled),
+                None
+            ]
+        );
+        assert_eq!(render(&n), org);
+    }
+
+    #[test]
+    fn convert_from_markdown() {
+        let md = "---
+title: Notes
+tags: [work, home]
+---
+
+# Plan
+
+Some **bold** and `code` with a [link](https://example.com).
+
+* [x] done
+* [ ] open
+
+```
+** not a heading
+```
+
+> quoted
+";
+        let n = crate::Markdown::parse(md).unwrap();
+        let org = render(&n);
+        assert_eq!(
+            org,
+            "#+title: Notes
+#+tags: work home
+
+* Plan
+
+Some *bold* and ~code~ with a [[https://example.com][link]].
+
+- [X] done
+- [ ] open
+
+#+begin_example
+,** not a heading
+#+end_example
+
+#+begin_quote
+quoted
+#+end_quote
+"
+        );
+        assert_eq!(Org::parse(&org).unwrap().count_tasks(), n.count_tasks());
+    }
+}
@@ -0,0 +1,282 @@
+use crate::{
+    visit::{walk_block, walk_inline, walk_list_item, Visit},
+    Block, BlockKind, Inline, ListItem, Nodo, Span,
+};
+use std::slice;
+
+/// A depth-first iterator over every block in a nodo, including those nested in quotes, list
+/// items and footnote definitions.
+pub struct Blocks<'a> {
+    stack: Vec<slice::Iter<'a, Block>>,
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = &'a Block;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(block) = self.stack.last_mut()?.next() else {
+                self.stack.pop();
+                continue;
+            };
+
+            match &block.kind {
+                BlockKind::Quote(blocks) | BlockKind::FootnoteDefinition(_, blocks) => {
+                    self.stack.push(blocks.iter());
+                }
+                BlockKind::List(_, items) => {
+                    for item in items.iter().rev() {
+                        self.stack.push(item.blocks.iter());
+                    }
+                }
+                BlockKind::Paragraph(_)
+                | BlockKind::Heading(_, _, _)
+                | BlockKind::Code(_, _)
+                | BlockKind::Table(_, _, _)
+                | BlockKind::Html(_)
+                | BlockKind::ReferenceDefinition(_, _, _)
+                | BlockKind::Rule
+                | BlockKind::Raw(_) => {}
+            }
+
+            return Some(block);
+        }
+    }
+}
+
+/// A depth-first iterator over every inline in a nodo, including those nested in emphasis and
+/// other inlines.
+pub struct Inlines<'a> {
+    blocks: Blocks<'a>,
+    stack: Vec<slice::Iter<'a, Inline>>,
+}
+
+impl<'a> Iterator for Inlines<'a> {
+    type Item = &'a Inline;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(iter) = self.stack.last_mut() else {
+                match &self.blocks.next()?.kind {
+                    BlockKind::Paragraph(inlines) | BlockKind::Heading(_, _, inlines) => {
+                        self.stack.push(inlines.iter());
+                    }
+                    BlockKind::Table(_, header, rows) => {
+                        for cell in rows.iter().flatten().rev() {
+                            self.stack.push(cell.iter());
+                        }
+                        for cell in header.iter().rev() {
+                            self.stack.push(cell.iter());
+                        }
+                    }
+                    BlockKind::Code(_, _)
+                    | BlockKind::Quote(_)
+                    | BlockKind::List(_, _)
+                    | BlockKind::FootnoteDefinition(_, _)
+                    | BlockKind::Html(_)
+                    | BlockKind::ReferenceDefinition(_, _, _)
+                    | BlockKind::Rule
+                    | BlockKind::Raw(_) => {}
+                }
+                continue;
+            };
+
+            let Some(inline) = iter.next() else {
+                self.stack.pop();
+                continue;
+            };
+
+            match inline {
+                Inline::Emph(inlines)
+                | Inline::Strong(inlines)
+                | Inline::Strikethrough(inlines) => self.stack.push(inlines.iter()),
+                Inline::Plain(_)
+                | Inline::Code(_)
+                | Inline::Link(_)
+                | Inline::Image(_)
+                | Inline::Html(_)
+                | Inline::FootnoteReference(_)
+                | Inline::SoftBreak
+                | Inline::HardBreak
+                | Inline::Raw(_) => {}
+            }
+
+            return Some(inline);
+        }
+    }
+}
+
+/// Collects list items in document order.
+struct ListItems<'a>(Vec<&'a ListItem>);
+
+impl<'a> Visit<'a> for ListItems<'a> {
+    fn visit_list_item(&mut self, i: &'a ListItem) {
+        self.0.push(i);
+        walk_list_item(self, i);
+    }
+}
+
+/// Collects the spans of the blocks that have raw source in them, in document order.
+#[derive(Default)]
+struct RawSpans {
+    spans: Vec<Span>,
+    /// The span of the innermost block being visited.
+    block: Span,
+}
+
+impl<'a> Visit<'a> for RawSpans {
+    fn visit_block(&mut self, b: &'a Block) {
+        if let BlockKind::Raw(_) = b.kind {
+            self.spans.push(b.span);
+            return;
+        }
+        let outer = std::mem::replace(&mut self.block, b.span);
+        walk_block(self, b);
+        self.block = outer;
+    }
+
+    fn visit_inline(&mut self, i: &'a Inline) {
+        if let Inline::Raw(_) = i {
+            if self.spans.last() != Some(&self.block) {
+                self.spans.push(self.block);
+            }
+        }
+        walk_inline(self, i);
+    }
+}
+
+impl Nodo {
+    /// Iterate over all blocks in the nodo, in document order.
+    #[must_use]
+    pub fn iter_blocks(&self) -> Blocks<'_> {
+        Blocks {
+            stack: vec![self.blocks.iter()],
+        }
+    }
+
+    /// Iterate over all inlines in the nodo, in document order.
+    #[must_use]
+    pub fn iter_inlines(&self) -> Inlines<'_> {
+        Inlines {
+            blocks: self.iter_blocks(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// The level and content of each heading.
+    pub fn headings(&self) -> impl Iterator<Item = (u32, &[Inline])> {
+        self.iter_blocks().filter_map(|b| {
+            if let BlockKind::Heading(level, _, inlines) = &b.kind {
+                Some((*level, inlines.as_slice()))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The language and content of each code block.
+    pub fn code_blocks(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.iter_blocks().filter_map(|b| {
+            if let BlockKind::Code(lang, content) = &b.kind {
+                Some((lang.as_str(), content.as_str()))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The text and destination of each link.
+    pub fn links(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.iter_inlines().filter_map(|i| {
+            if let Inline::Link(link) = i {
+                Some((link.text.as_str(), link.destination.as_str()))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Every list item, including those in nested lists, in document order.
+    pub fn list_items(&self) -> impl Iterator<Item = &ListItem> {
+        let mut items = ListItems(Vec::new());
+        items.visit_nodo(self);
+        items.0.into_iter()
+    }
+
+    /// The spans of the blocks that keep some of their source raw, as raw blocks or in raw
+    /// inlines, in document order.
+    ///
+    /// For a nodo parsed leniently these are where the source couldn't be parsed.
+    pub fn raw_spans(&self) -> impl Iterator<Item = Span> {
+        let mut spans = RawSpans::default();
+        spans.visit_nodo(self);
+        spans.spans.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{markdown::ParseOptions, ListItem, Markdown, Parse, TaskState};
+    use pretty_assertions::assert_eq;
+
+    const MD: &str = "# top
+
+- [ ] a [link](one)
+    - nested *[emphasised](two)*
+
+> ## quoted
+>
+> ```sh
+> echo
+> ```
+
+- [x] last
+";
+
+    #[test]
+    fn headings() {
+        let nodo = Markdown::parse(MD).unwrap();
+        let levels = nodo.headings().map(|(l, _)| l).collect::<Vec<_>>();
+        assert_eq!(levels, vec![1, 2]);
+    }
+
+    #[test]
+    fn links() {
+        let nodo = Markdown::parse(MD).unwrap();
+        let dests = nodo.links().map(|(_, d)| d).collect::<Vec<_>>();
+        assert_eq!(dests, vec!["one", "two"]);
+    }
+
+    #[test]
+    fn code_blocks() {
+        let nodo = Markdown::parse(MD).unwrap();
+        assert_eq!(
+            nodo.code_blocks().collect::<Vec<_>>(),
+            vec![("sh", "echo\n")]
+        );
+    }
+
+    #[test]
+    fn list_items() {
+        let nodo = Markdown::parse(MD).unwrap();
+        let tasks = nodo.list_items().map(ListItem::task).collect::<Vec<_>>();
+        assert_eq!(
+            tasks,
+            vec![Some(TaskState::Open), None, Some(TaskState::Done)]
+        );
+    }
+
+    #[test]
+    fn raw_spans() {
+        let md = "# top
+
+parsed
+
+- [*emphasised* link](dest) and [*another*](dest)
+";
+        let nodo = Markdown::parse_with(md, ParseOptions { lenient: true }).unwrap();
+        let lines = nodo.raw_spans().map(|s| s.start.line).collect::<Vec<_>>();
+        assert_eq!(lines, vec![5]);
+        assert_eq!(Markdown::parse(MD).unwrap().raw_spans().count(), 0);
+    }
+}
@@ -1 +1,28 @@
+use crate::Inline;
+
+pub mod iter;
 pub mod tasks;
+
+/// The text of some inlines with all formatting removed.
+///
+/// Line breaks become spaces, images are replaced by their alt text and raw source is kept as is.
+#[must_use]
+pub fn plain_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    push_plain_text(inlines, &mut text);
+    text
+}
+
+fn push_plain_text(inlines: &[Inline], text: &mut String) {
+    for inline in inlines {
+        match inline {
+            Inline::Plain(s) | Inline::Code(s) | Inline::Raw(s) => text.push_str(s),
+            Inline::Link(link) | Inline::Image(link) => text.push_str(&link.text),
+            Inline::Emph(inlines) | Inline::Strong(inlines) | Inline::Strikethrough(inlines) => {
+                push_plain_text(inlines, text);
+            }
+            Inline::SoftBreak | Inline::HardBreak => text.push(' '),
+            Inline::Html(_) | Inline::FootnoteReference(_) => {}
+        }
+    }
+}
@@ -1,34 +1,569 @@
-use crate::{Block, Nodo};
+use crate::{
+    query::plain_text,
+    visit::{walk_block, walk_list_item, Visit},
+    Block, BlockKind, Inline, ListItem, Nodo, TaskState,
+};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
 
+/// The number of tasks that are done out of the total, where cancelled tasks aren't counted.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct Progress {
+    pub completed: u32,
+    pub total: u32,
+}
+
+impl Progress {
+    fn add(&mut self, state: TaskState) {
+        if state == TaskState::Cancelled {
+            return;
+        }
+        self.total += 1;
+        if state == TaskState::Done {
+            self.completed += 1;
+        }
+    }
+}
+
+/// A task along with the tasks nested beneath it.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TaskTree {
+    pub state: TaskState,
+    pub subtasks: Vec<Self>,
+}
+
+impl TaskTree {
+    /// The progress of all tasks nested beneath this one.
+    #[must_use]
+    pub fn progress(&self) -> Progress {
+        let mut progress = Progress::default();
+        for t in &self.subtasks {
+            progress.add(t.state);
+            let sub = t.progress();
+            progress.completed += sub.completed;
+            progress.total += sub.total;
+        }
+        progress
+    }
+
+    /// Whether this task is complete, judged by its subtasks if it has any that aren't
+    /// cancelled.
+    #[must_use]
+    pub fn derived_completion(&self) -> bool {
+        let mut subtasks = self
+            .subtasks
+            .iter()
+            .filter(|t| t.state != TaskState::Cancelled)
+            .peekable();
+        if subtasks.peek().is_none() {
+            self.state == TaskState::Done
+        } else {
+            subtasks.all(Self::derived_completion)
+        }
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
 pub struct TaskCount {
     pub completed: u32,
+    /// The number of tasks, not including those that were cancelled.
     pub total: u32,
+    pub in_progress: u32,
+    /// The progress of tasks at each depth, where depth is the number of tasks a task is nested
+    /// beneath.
+    pub by_depth: Vec<Progress>,
+    /// The top level tasks, each with their subtasks.
+    pub tasks: Vec<TaskTree>,
+}
+
+#[derive(Default)]
+struct TaskCounter {
+    count: TaskCount,
+    parents: Vec<TaskTree>,
+}
+
+impl TaskCounter {
+    /// Count a task, with `walk` counting the tasks nested beneath it.
+    fn add(&mut self, state: TaskState, walk: impl FnOnce(&mut Self)) {
+        match state {
+            TaskState::Done => self.count.completed += 1,
+            TaskState::InProgress => self.count.in_progress += 1,
+            TaskState::Open | TaskState::Cancelled | TaskState::Deferred => {}
+        }
+        if state != TaskState::Cancelled {
+            self.count.total += 1;
+        }
+
+        let depth = self.parents.len();
+        if self.count.by_depth.len() <= depth {
+            self.count.by_depth.push(Progress::default());
+        }
+        self.count.by_depth[depth].add(state);
+
+        self.parents.push(TaskTree {
+            state,
+            subtasks: Vec::new(),
+        });
+        walk(self);
+        let task = self.parents.pop().unwrap();
+
+        match self.parents.last_mut() {
+            Some(parent) => parent.subtasks.push(task),
+            None => self.count.tasks.push(task),
+        }
+    }
+}
+
+impl<'a> Visit<'a> for TaskCounter {
+    fn visit_block(&mut self, b: &'a Block) {
+        match b.kind {
+            // the tasks in the section of a headline task aren't nested beneath it, as the
+            // section isn't part of the heading
+            BlockKind::Heading(_, Some(state), _) => self.add(state, |c| walk_block(c, b)),
+            _ => walk_block(self, b),
+        }
+    }
+
+    fn visit_list_item(&mut self, i: &'a ListItem) {
+        match i.task {
+            Some(state) => self.add(state, |c| walk_list_item(c, i)),
+            None => walk_list_item(self, i),
+        }
+    }
+}
+
+/// A task found in a nodo.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Task {
+    /// The plain text of the first paragraph of the task.
+    pub text: String,
+    pub state: TaskState,
+    /// The text of the headings that the task is under, outermost first.
+    pub headings: Vec<String>,
+    /// The index of the task that this one is nested beneath, if any.
+    pub parent: Option<usize>,
+    /// The line in the source that the task started on, counting from 1.
+    pub line: usize,
+    /// The date from a `due:YYYY-MM-DD` in the text.
+    pub due: Option<Date>,
+    /// The priority from a `!high` or `(A)` style marker in the text.
+    pub priority: Option<Priority>,
+    /// The names of `#tag`s in the text, without the `#`.
+    pub tags: Vec<String>,
+    /// The names of `@context`s in the text, without the `@`.
+    pub contexts: Vec<String>,
+    /// The names of `+project`s in the text, without the `+`.
+    pub projects: Vec<String>,
+}
+
+/// A calendar date, written as `YYYY-MM-DD`.
+///
+/// Dates order chronologically.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+#[derive(Error, Debug, Eq, PartialEq)]
+#[error("invalid date '{0}', expected YYYY-MM-DD")]
+pub struct DateError(String);
+
+impl FromStr for Date {
+    type Err = DateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || DateError(s.to_string());
+        let mut parts = s.splitn(3, '-');
+        let mut part = |len: usize| {
+            parts
+                .next()
+                .filter(|p| p.len() == len && p.bytes().all(|b| b.is_ascii_digit()))
+                .ok_or_else(err)
+        };
+        let date = Self {
+            year: part(4)?.parse().map_err(|_| err())?,
+            month: part(2)?.parse().map_err(|_| err())?,
+            day: part(2)?.parse().map_err(|_| err())?,
+        };
+
+        let leap = date.year.is_multiple_of(4)
+            && (!date.year.is_multiple_of(100) || date.year.is_multiple_of(400));
+        let days = match date.month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => return Err(err()),
+        };
+        if date.day == 0 || date.day > days {
+            return Err(err());
+        }
+        Ok(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// The priority of a task.
+///
+/// Priorities order from highest to lowest so that sorting puts the most important first.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
+pub enum Priority {
+    /// `!high` or `(A)`
+    High,
+    /// `!medium` or `(B)`
+    Medium,
+    /// `!low` or `(C)`
+    Low,
+}
+
+impl Priority {
+    fn from_marker(s: &str) -> Option<Self> {
+        match s {
+            "!high" | "(A)" => Some(Self::High),
+            "!medium" | "(B)" => Some(Self::Medium),
+            "!low" | "(C)" => Some(Self::Low),
+            _ => None,
+        }
+    }
+}
+
+impl Task {
+    /// Fill in the metadata of this task from the words of its text.
+    ///
+    /// Only plain text is read, so a `#` in inline code or a link destination isn't a tag. The
+    /// first due date and priority win over any later ones.
+    fn read_metadata(&mut self, inlines: &[Inline]) {
+        for inline in inlines {
+            match inline {
+                Inline::Plain(s) => {
+                    for word in s.split_whitespace() {
+                        self.read_word(word);
+                    }
+                }
+                Inline::Emph(inlines)
+                | Inline::Strong(inlines)
+                | Inline::Strikethrough(inlines) => self.read_metadata(inlines),
+                Inline::Code(_)
+                | Inline::Link(_)
+                | Inline::Image(_)
+                | Inline::Html(_)
+                | Inline::FootnoteReference(_)
+                | Inline::SoftBreak
+                | Inline::HardBreak
+                | Inline::Raw(_) => {}
+            }
+        }
+    }
+
+    fn read_word(&mut self, word: &str) {
+        // punctuation ending a sentence isn't part of the word
+        let word = word.trim_end_matches([',', ';', ':', '.', '!', '?']);
+        if let Some(priority) = Priority::from_marker(word) {
+            self.priority.get_or_insert(priority);
+        } else if let Some(date) = word.strip_prefix("due:") {
+            if let Ok(date) = date.parse() {
+                self.due.get_or_insert(date);
+            }
+        } else if let Some(name) = word.strip_prefix('#').filter(|n| is_name(n)) {
+            self.tags.push(name.to_string());
+        } else if let Some(name) = word.strip_prefix('@').filter(|n| is_name(n)) {
+            self.contexts.push(name.to_string());
+        } else if let Some(name) = word.strip_prefix('+').filter(|n| is_name(n)) {
+            self.projects.push(name.to_string());
+        }
+    }
+}
+
+/// Whether a word after a `#`, `@` or `+` is a name, rather than something like `#1` or `+=`.
+fn is_name(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_alphabetic)
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/' | '.'))
+}
+
+#[derive(Default)]
+struct TaskCollector {
+    tasks: Vec<Task>,
+    headings: Vec<(u32, String)>,
+    parents: Vec<usize>,
+}
+
+impl<'a> Visit<'a> for TaskCollector {
+    fn visit_block(&mut self, b: &'a Block) {
+        if let BlockKind::Heading(level, task, inlines) = &b.kind {
+            while self.headings.last().is_some_and(|(l, _)| l >= level) {
+                self.headings.pop();
+            }
+            if let Some(state) = task {
+                self.push(*state, inlines, b.span.start.line);
+            }
+            self.headings.push((*level, plain_text(inlines)));
+        }
+        walk_block(self, b);
+    }
+
+    fn visit_list_item(&mut self, i: &'a ListItem) {
+        let Some(state) = i.task else {
+            return walk_list_item(self, i);
+        };
+
+        let inlines = match i.blocks.first().map(|b| &b.kind) {
+            Some(BlockKind::Paragraph(inlines)) => inlines.as_slice(),
+            _ => &[],
+        };
+        self.push(state, inlines, i.span.start.line);
+
+        self.parents.push(self.tasks.len() - 1);
+        walk_list_item(self, i);
+        self.parents.pop();
+    }
+}
+
+impl TaskCollector {
+    fn push(&mut self, state: TaskState, inlines: &[Inline], line: usize) {
+        let mut task = Task {
+            text: plain_text(inlines),
+            state,
+            headings: self.headings.iter().map(|(_, h)| h.clone()).collect(),
+            parent: self.parents.last().copied(),
+            line,
+            due: None,
+            priority: None,
+            tags: Vec::new(),
+            contexts: Vec::new(),
+            projects: Vec::new(),
+        };
+        task.read_metadata(inlines);
+        self.tasks.push(task);
+    }
 }
 
 impl Nodo {
+    /// All of the tasks in this nodo, in document order.
+    #[must_use]
+    pub fn tasks(&self) -> Vec<Task> {
+        let mut collector = TaskCollector::default();
+        collector.visit_nodo(self);
+        collector.tasks
+    }
+
+    /// Count the tasks in this nodo, including those nested in other lists and quotes.
     #[must_use]
     pub fn count_tasks(&self) -> TaskCount {
-        let mut completed = 0;
-        let mut total = 0;
-        for block in &self.blocks {
-            match block {
-                Block::List(_, items) => {
-                    for item in items.iter() {
-                        if let Some(c) = item.task {
-                            total += 1;
-                            if c {
-                                completed += 1;
-                            }
-                        }
-                    }
+        let mut counter = TaskCounter::default();
+        counter.visit_nodo(self);
+        counter.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Markdown, Org, Parse};
+    use pretty_assertions::assert_eq;
+
+    const MD: &str = "- [ ] parent
+    - [x] done child
+    - a note
+        - [x] nested under a note
+- [x] other
+
+> - [ ] quoted
+";
+
+    #[test]
+    fn count_tasks_recursively() {
+        let count = Markdown::parse(MD).unwrap().count_tasks();
+
+        assert_eq!(count.completed, 3);
+        assert_eq!(count.total, 5);
+        assert_eq!(
+            count.by_depth,
+            vec![
+                Progress {
+                    completed: 1,
+                    total: 3
+                },
+                Progress {
+                    completed: 2,
+                    total: 2
                 }
-                Block::Paragraph(_)
-                | Block::Heading(_, _)
-                | Block::Code(_, _)
-                | Block::Quote(_)
-                | Block::Rule => {}
+            ]
+        );
+    }
+
+    #[test]
+    fn count_tasks_by_parent() {
+        let count = Markdown::parse(MD).unwrap().count_tasks();
+        let parent = &count.tasks[0];
+
+        assert_eq!(count.tasks.len(), 3);
+        assert_eq!(
+            parent.progress(),
+            Progress {
+                completed: 2,
+                total: 2
             }
+        );
+        assert_eq!(parent.state, TaskState::Open);
+        assert!(parent.derived_completion());
+    }
+
+    #[test]
+    fn count_task_states() {
+        let md = "- [-] started
+    - [x] first step
+    - [~] dropped step
+- [~] dropped
+- [>] later
+- [ ] open
+";
+        let count = Markdown::parse(md).unwrap().count_tasks();
+
+        assert_eq!(count.completed, 1);
+        assert_eq!(count.total, 4);
+        assert_eq!(count.in_progress, 1);
+        assert_eq!(count.tasks[0].state, TaskState::InProgress);
+        assert!(count.tasks[0].derived_completion());
+    }
+
+    #[test]
+    fn tasks() {
+        let md = "# Project
+
+- [ ] write *docs*
+    - [x] outline
+
+## Later
+
+- [ ] release `v1`
+";
+        let tasks = Markdown::parse(md).unwrap().tasks();
+
+        assert_eq!(
+            tasks,
+            vec![
+                Task {
+                    text: "write docs".to_string(),
+                    state: TaskState::Open,
+                    headings: vec!["Project".to_string()],
+                    parent: None,
+                    line: 3,
+                    due: None,
+                    priority: None,
+                    tags: Vec::new(),
+                    contexts: Vec::new(),
+                    projects: Vec::new(),
+                },
+                Task {
+                    text: "outline".to_string(),
+                    state: TaskState::Done,
+                    headings: vec!["Project".to_string()],
+                    parent: Some(0),
+                    line: 4,
+                    due: None,
+                    priority: None,
+                    tags: Vec::new(),
+                    contexts: Vec::new(),
+                    projects: Vec::new(),
+                },
+                Task {
+                    text: "release v1".to_string(),
+                    state: TaskState::Open,
+                    headings: vec!["Project".to_string(), "Later".to_string()],
+                    parent: None,
+                    line: 8,
+                    due: None,
+                    priority: None,
+                    tags: Vec::new(),
+                    contexts: Vec::new(),
+                    projects: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn count_org_headline_tasks() {
+        let org = "* TODO headline task
+* DONE done one
+- [ ] checkbox
+";
+        let nodo = Org::parse(org).unwrap();
+        let count = nodo.count_tasks();
+
+        assert_eq!(count.completed, 1);
+        assert_eq!(count.total, 3);
+        assert_eq!(count.tasks.len(), 3);
+
+        let tasks = nodo.tasks();
+        assert_eq!(tasks[0].text, "headline task");
+        assert_eq!(tasks[0].state, TaskState::Open);
+        assert_eq!(tasks[2].headings, vec!["done one".to_string()]);
+    }
+
+    #[test]
+    fn task_metadata() {
+        let md = "- [ ] (A) call @phone about +launch due:2026-11-01 #work, #ops
+- [ ] review `#notatag` !low due:2026-02-30 #1
+- [ ] *#urgent* fix [#link](#anchor) !high !low
+";
+        let tasks = Markdown::parse(md).unwrap().tasks();
+
+        let first = &tasks[0];
+        assert_eq!(
+            first.due,
+            Some(Date {
+                year: 2026,
+                month: 11,
+                day: 1
+            })
+        );
+        assert_eq!(first.priority, Some(Priority::High));
+        assert_eq!(first.tags, vec!["work", "ops"]);
+        assert_eq!(first.contexts, vec!["phone"]);
+        assert_eq!(first.projects, vec!["launch"]);
+
+        let second = &tasks[1];
+        assert_eq!(second.due, None);
+        assert_eq!(second.priority, Some(Priority::Low));
+        assert!(second.tags.is_empty());
+
+        let third = &tasks[2];
+        assert_eq!(third.priority, Some(Priority::High));
+        assert_eq!(third.tags, vec!["urgent"]);
+    }
+
+    #[test]
+    fn parse_dates() {
+        assert_eq!(
+            "2024-02-29".parse(),
+            Ok(Date {
+                year: 2024,
+                month: 2,
+                day: 29
+            })
+        );
+        assert_eq!(
+            "2024-02-29".parse::<Date>().unwrap().to_string(),
+            "2024-02-29"
+        );
+        for invalid in &[
+            "2023-02-29",
+            "2024-13-01",
+            "2024-1-01",
+            "2024-01-01x",
+            "today",
+        ] {
+            assert_eq!(invalid.parse::<Date>(), Err(DateError(invalid.to_string())));
         }
-        TaskCount { completed, total }
     }
 }
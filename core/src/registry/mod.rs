@@ -0,0 +1,238 @@
+//! The file formats that nodos can be stored in, keyed by file extension.
+//!
+//! Frontends look up the format of a file here rather than naming a format directly, so that a
+//! format works everywhere once it is registered.
+
+use crate::{markdown::ParseOptions, Markdown, Nodo, Org, Parse, Render};
+use std::{error, io, path::Path};
+use thiserror::Error;
+
+type BoxError = Box<dyn error::Error + Send + Sync>;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Parse(BoxError),
+
+    #[error(transparent)]
+    Render(BoxError),
+}
+
+/// A format that nodos can be parsed from and rendered to.
+#[derive(Clone)]
+pub struct FileFormat {
+    name: &'static str,
+    extensions: &'static [&'static str],
+    lenient: bool,
+    parse: fn(&str, ParseOptions) -> Result<Nodo, Error>,
+    render: fn(&Nodo, &mut dyn io::Write) -> Result<(), Error>,
+}
+
+impl std::fmt::Debug for FileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileFormat")
+            .field("name", &self.name)
+            .field("extensions", &self.extensions)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FileFormat {
+    /// A format using the [`Parse`] and [`Render`] implementations of `T`.
+    ///
+    /// The first extension is the one used for new files.
+    #[must_use]
+    pub fn new<T>(name: &'static str, extensions: &'static [&'static str]) -> Self
+    where
+        T: Parse + Render,
+        T::ParseError: error::Error + Send + Sync + 'static,
+        T::RenderError: error::Error + Send + Sync + 'static,
+    {
+        Self {
+            name,
+            extensions,
+            lenient: false,
+            parse: |s, _| T::parse(s).map_err(|e| Error::Parse(Box::new(e))),
+            render: |n, mut w| T::render(n, &mut w).map_err(|e| Error::Render(Box::new(e))),
+        }
+    }
+
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    #[must_use]
+    pub const fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+
+    /// Whether a lenient parse keeps source that can't be parsed as raw blocks and inlines, so
+    /// that the [`Nodo::raw_spans`] of a nodo are where it couldn't be parsed.
+    ///
+    /// Other formats can have raw blocks of their own, such as org drawers.
+    #[must_use]
+    pub const fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// Parse a nodo in this format.
+    ///
+    /// The options only apply to formats that support them, such as the lenient parsing of
+    /// markdown.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the source isn't valid for this format.
+    pub fn parse(&self, s: &str, options: ParseOptions) -> Result<Nodo, Error> {
+        (self.parse)(s, options)
+    }
+
+    /// Render a nodo in this format.
+    ///
+    /// # Errors
+    ///
+    /// Errors if writing fails or the nodo can't be represented in this format.
+    pub fn render(&self, n: &Nodo, w: &mut dyn io::Write) -> Result<(), Error> {
+        (self.render)(n, w)
+    }
+}
+
+/// A set of formats, looked up by file extension.
+#[derive(Debug, Clone)]
+pub struct Registry {
+    formats: Vec<FileFormat>,
+}
+
+impl Default for Registry {
+    /// A registry with all of the formats in this crate.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(FileFormat {
+            lenient: true,
+            parse: |s, options| {
+                Markdown::parse_with(s, options).map_err(|e| Error::Parse(Box::new(e)))
+            },
+            ..FileFormat::new::<Markdown>("markdown", &["md", "markdown"])
+        });
+        registry.register(FileFormat::new::<Org>("org", &["org"]));
+        #[cfg(feature = "serde")]
+        registry.register(FileFormat::new::<crate::Json>("json", &["json"]));
+        registry
+    }
+}
+
+impl Registry {
+    /// A registry without any formats.
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self {
+            formats: Vec::new(),
+        }
+    }
+
+    /// Add a format, taking over its extensions from any format registered before it.
+    pub fn register(&mut self, format: FileFormat) {
+        self.formats.insert(0, format);
+    }
+
+    /// The format for files with the given extension, ignoring case.
+    #[must_use]
+    pub fn by_extension(&self, extension: &str) -> Option<&FileFormat> {
+        self.formats.iter().find(|f| {
+            f.extensions
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension))
+        })
+    }
+
+    /// The format for a file, from its extension.
+    #[must_use]
+    pub fn by_path(&self, path: &Path) -> Option<&FileFormat> {
+        self.by_extension(path.extension()?.to_str()?)
+    }
+
+    /// The format with the given name.
+    #[must_use]
+    pub fn by_name(&self, name: &str) -> Option<&FileFormat> {
+        self.formats.iter().find(|f| f.name == name)
+    }
+
+    /// All of the registered formats, most recently registered first.
+    pub fn formats(&self) -> impl Iterator<Item = &FileFormat> {
+        self.formats.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn lookup_by_extension() {
+        let registry = Registry::default();
+
+        assert_eq!(
+            registry
+                .by_path(Path::new("notes/todo.MD"))
+                .map(FileFormat::name),
+            Some("markdown")
+        );
+        assert_eq!(
+            registry
+                .by_path(Path::new("notes/todo.org"))
+                .map(FileFormat::name),
+            Some("org")
+        );
+        assert!(registry.by_path(Path::new("notes/todo.txt")).is_none());
+        assert!(registry.by_path(Path::new("notes/todo")).is_none());
+    }
+
+    #[test]
+    fn later_formats_take_over_extensions() {
+        struct Empty;
+
+        impl Parse for Empty {
+            type ParseError = io::Error;
+
+            fn parse(_: &str) -> Result<Nodo, Self::ParseError> {
+                Ok(Nodo::default())
+            }
+        }
+
+        impl Render for Empty {
+            type RenderError = io::Error;
+
+            fn render<W: io::Write>(_: &Nodo, _: &mut W) -> Result<(), Self::RenderError> {
+                Ok(())
+            }
+        }
+
+        let mut registry = Registry::default();
+        registry.register(FileFormat::new::<Empty>("empty", &["markdown", "empty"]));
+
+        assert_eq!(
+            registry.by_extension("markdown").map(FileFormat::name),
+            Some("empty")
+        );
+        assert_eq!(
+            registry.by_extension("md").map(FileFormat::name),
+            Some("markdown")
+        );
+    }
+
+    #[test]
+    fn parse_and_render() {
+        let registry = Registry::default();
+        let markdown = registry.by_name("markdown").unwrap();
+
+        let md = "# Title\n\n[*emphasised* link](dest)\n";
+        assert!(markdown.parse(md, ParseOptions::default()).is_err());
+
+        let nodo = markdown.parse(md, ParseOptions { lenient: true }).unwrap();
+        let mut out = Vec::new();
+        markdown.render(&nodo, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), md);
+    }
+}
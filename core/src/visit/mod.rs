@@ -0,0 +1,275 @@
+//! Traversal of the nodo AST.
+//!
+//! [`Visit`] walks a nodo by reference and [`VisitMut`] walks it by mutable reference so that
+//! nodes can be rewritten in place. Every method has a default implementation that recurses into
+//! the children of the node through the matching `walk_*` function, so implementors only need to
+//! override the methods for the nodes they care about. An overriding method can call the `walk_*`
+//! function itself to continue into the children.
+
+use crate::{Block, BlockKind, Inline, ListItem, Nodo};
+
+pub trait Visit<'a> {
+    fn visit_nodo(&mut self, n: &'a Nodo) {
+        walk_nodo(self, n);
+    }
+
+    fn visit_blocks(&mut self, bs: &'a [Block]) {
+        walk_blocks(self, bs);
+    }
+
+    fn visit_block(&mut self, b: &'a Block) {
+        walk_block(self, b);
+    }
+
+    fn visit_list_item(&mut self, i: &'a ListItem) {
+        walk_list_item(self, i);
+    }
+
+    fn visit_inlines(&mut self, is: &'a [Inline]) {
+        walk_inlines(self, is);
+    }
+
+    fn visit_inline(&mut self, i: &'a Inline) {
+        walk_inline(self, i);
+    }
+}
+
+pub fn walk_nodo<'a, V: Visit<'a> + ?Sized>(v: &mut V, n: &'a Nodo) {
+    v.visit_blocks(&n.blocks);
+}
+
+pub fn walk_blocks<'a, V: Visit<'a> + ?Sized>(v: &mut V, bs: &'a [Block]) {
+    for b in bs {
+        v.visit_block(b);
+    }
+}
+
+pub fn walk_block<'a, V: Visit<'a> + ?Sized>(v: &mut V, b: &'a Block) {
+    match &b.kind {
+        BlockKind::Paragraph(inlines) | BlockKind::Heading(_, _, inlines) => {
+            v.visit_inlines(inlines);
+        }
+        BlockKind::Quote(blocks) | BlockKind::FootnoteDefinition(_, blocks) => {
+            v.visit_blocks(blocks);
+        }
+        BlockKind::List(_, items) => {
+            for item in items {
+                v.visit_list_item(item);
+            }
+        }
+        BlockKind::Table(_, header, rows) => {
+            for cell in header.iter().chain(rows.iter().flatten()) {
+                v.visit_inlines(cell);
+            }
+        }
+        BlockKind::Code(_, _)
+        | BlockKind::Html(_)
+        | BlockKind::ReferenceDefinition(_, _, _)
+        | BlockKind::Rule
+        | BlockKind::Raw(_) => {}
+    }
+}
+
+pub fn walk_list_item<'a, V: Visit<'a> + ?Sized>(v: &mut V, i: &'a ListItem) {
+    v.visit_blocks(&i.blocks);
+}
+
+pub fn walk_inlines<'a, V: Visit<'a> + ?Sized>(v: &mut V, is: &'a [Inline]) {
+    for i in is {
+        v.visit_inline(i);
+    }
+}
+
+pub fn walk_inline<'a, V: Visit<'a> + ?Sized>(v: &mut V, i: &'a Inline) {
+    match i {
+        Inline::Emph(inlines) | Inline::Strong(inlines) | Inline::Strikethrough(inlines) => {
+            v.visit_inlines(inlines);
+        }
+        Inline::Plain(_)
+        | Inline::Code(_)
+        | Inline::Link(_)
+        | Inline::Image(_)
+        | Inline::Html(_)
+        | Inline::FootnoteReference(_)
+        | Inline::SoftBreak
+        | Inline::HardBreak
+        | Inline::Raw(_) => {}
+    }
+}
+
+/// The mutable counterpart to [`Visit`].
+///
+/// The `*s_mut` methods take the containing `Vec` so that implementors can insert or remove
+/// nodes as well as change them.
+pub trait VisitMut {
+    fn visit_nodo_mut(&mut self, n: &mut Nodo) {
+        walk_nodo_mut(self, n);
+    }
+
+    fn visit_blocks_mut(&mut self, bs: &mut Vec<Block>) {
+        walk_blocks_mut(self, bs);
+    }
+
+    fn visit_block_mut(&mut self, b: &mut Block) {
+        walk_block_mut(self, b);
+    }
+
+    fn visit_list_item_mut(&mut self, i: &mut ListItem) {
+        walk_list_item_mut(self, i);
+    }
+
+    fn visit_inlines_mut(&mut self, is: &mut Vec<Inline>) {
+        walk_inlines_mut(self, is);
+    }
+
+    fn visit_inline_mut(&mut self, i: &mut Inline) {
+        walk_inline_mut(self, i);
+    }
+}
+
+pub fn walk_nodo_mut<V: VisitMut + ?Sized>(v: &mut V, n: &mut Nodo) {
+    v.visit_blocks_mut(&mut n.blocks);
+}
+
+pub fn walk_blocks_mut<V: VisitMut + ?Sized>(v: &mut V, bs: &mut Vec<Block>) {
+    for b in bs {
+        v.visit_block_mut(b);
+    }
+}
+
+pub fn walk_block_mut<V: VisitMut + ?Sized>(v: &mut V, b: &mut Block) {
+    match &mut b.kind {
+        BlockKind::Paragraph(inlines) | BlockKind::Heading(_, _, inlines) => {
+            v.visit_inlines_mut(inlines);
+        }
+        BlockKind::Quote(blocks) | BlockKind::FootnoteDefinition(_, blocks) => {
+            v.visit_blocks_mut(blocks);
+        }
+        BlockKind::List(_, items) => {
+            for item in items {
+                v.visit_list_item_mut(item);
+            }
+        }
+        BlockKind::Table(_, header, rows) => {
+            for cell in header.iter_mut().chain(rows.iter_mut().flatten()) {
+                v.visit_inlines_mut(cell);
+            }
+        }
+        BlockKind::Code(_, _)
+        | BlockKind::Html(_)
+        | BlockKind::ReferenceDefinition(_, _, _)
+        | BlockKind::Rule
+        | BlockKind::Raw(_) => {}
+    }
+}
+
+pub fn walk_list_item_mut<V: VisitMut + ?Sized>(v: &mut V, i: &mut ListItem) {
+    v.visit_blocks_mut(&mut i.blocks);
+}
+
+pub fn walk_inlines_mut<V: VisitMut + ?Sized>(v: &mut V, is: &mut Vec<Inline>) {
+    for i in is {
+        v.visit_inline_mut(i);
+    }
+}
+
+pub fn walk_inline_mut<V: VisitMut + ?Sized>(v: &mut V, i: &mut Inline) {
+    match i {
+        Inline::Emph(inlines) | Inline::Strong(inlines) | Inline::Strikethrough(inlines) => {
+            v.visit_inlines_mut(inlines);
+        }
+        Inline::Plain(_)
+        | Inline::Code(_)
+        | Inline::Link(_)
+        | Inline::Image(_)
+        | Inline::Html(_)
+        | Inline::FootnoteReference(_)
+        | Inline::SoftBreak
+        | Inline::HardBreak
+        | Inline::Raw(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Markdown, Parse, Render};
+    use pretty_assertions::assert_eq;
+
+    const MD: &str = "# a *heading*
+
+> quoted `code`
+
+- item
+    - nested **item**
+
+---
+
+| cell |
+| ---- |
+| text |
+";
+
+    #[test]
+    fn visit_reaches_every_plain_inline() {
+        struct Plains<'a>(Vec<&'a str>);
+
+        impl<'a> Visit<'a> for Plains<'a> {
+            fn visit_inline(&mut self, i: &'a Inline) {
+                if let Inline::Plain(s) = i {
+                    self.0.push(s);
+                }
+                walk_inline(self, i);
+            }
+        }
+
+        let nodo = Markdown::parse(MD).unwrap();
+        let mut plains = Plains(Vec::new());
+        plains.visit_nodo(&nodo);
+
+        assert_eq!(
+            plains.0,
+            vec!["a ", "heading", "quoted ", "item", "nested ", "item", "cell", "text"]
+        );
+    }
+
+    #[test]
+    fn visit_mut_rewrites_in_place() {
+        struct Rewrite;
+
+        impl VisitMut for Rewrite {
+            fn visit_blocks_mut(&mut self, bs: &mut Vec<Block>) {
+                bs.retain(|b| b.kind() != &BlockKind::Rule);
+                walk_blocks_mut(self, bs);
+            }
+
+            fn visit_inline_mut(&mut self, i: &mut Inline) {
+                if let Inline::Plain(s) = i {
+                    *s = s.to_uppercase();
+                }
+                walk_inline_mut(self, i);
+            }
+        }
+
+        let mut nodo = Markdown::parse(MD).unwrap();
+        Rewrite.visit_nodo_mut(&mut nodo);
+
+        let mut out = Vec::new();
+        Markdown::render(&nodo, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "# A *HEADING*
+
+> QUOTED `code`
+
+- ITEM
+    - NESTED **ITEM**
+
+| CELL |
+| ---- |
+| TEXT |
+"
+        );
+    }
+}